@@ -75,6 +75,12 @@ static mupdf_error_t *mupdf_new_error_from_str(const char *message)
     return err;
 }
 
+/* Raise an error from a callback implemented in Rust */
+void mupdf_throw(fz_context *ctx, const char *message)
+{
+    fz_throw(ctx, FZ_ERROR_GENERIC, "%s", message);
+}
+
 void mupdf_drop_error(mupdf_error_t *err)
 {
     if (err == NULL)
@@ -165,6 +171,220 @@ fz_rect mupdf_adjust_rect_for_stroke(fz_context *ctx, fz_rect self, fz_stroke_st
     return rect;
 }
 
+/* Colorspace */
+void mupdf_convert_color(fz_context *ctx, fz_colorspace *ss, const float *sv, fz_colorspace *ds, float *dv, fz_color_params cp, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        fz_convert_color(ctx, ss, sv, ds, dv, NULL, cp);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
+fz_colorspace *mupdf_new_icc_colorspace(fz_context *ctx, const unsigned char *data, size_t len, mupdf_error_t **errptr)
+{
+    fz_colorspace *cs = NULL;
+    fz_buffer *buf = NULL;
+    fz_var(buf);
+    fz_try(ctx)
+    {
+        buf = fz_new_buffer_from_copied_data(ctx, data, len);
+        cs = fz_new_icc_colorspace(ctx, FZ_COLORSPACE_NONE, 0, NULL, buf);
+    }
+    fz_always(ctx)
+    {
+        fz_drop_buffer(ctx, buf);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return cs;
+}
+
+fz_colorspace *mupdf_new_indexed_colorspace(fz_context *ctx, fz_colorspace *base, int high, const unsigned char *lookup, size_t len, mupdf_error_t **errptr)
+{
+    fz_colorspace *cs = NULL;
+    unsigned char *copy = NULL;
+    fz_var(copy);
+    if (high < 0 || high > 255 || len != (size_t)(high + 1) * fz_colorspace_n(ctx, base))
+    {
+        *errptr = mupdf_new_error_from_str("lookup table size does not match base colorspace");
+        return NULL;
+    }
+    fz_try(ctx)
+    {
+        copy = fz_malloc(ctx, len);
+        memcpy(copy, lookup, len);
+        /* The colorspace takes ownership of the lookup table */
+        cs = fz_new_indexed_colorspace(ctx, base, high, copy);
+    }
+    fz_catch(ctx)
+    {
+        fz_free(ctx, copy);
+        mupdf_save_error(ctx, errptr);
+    }
+    return cs;
+}
+
+typedef void (mupdf_tint_eval_fn)(fz_context *ctx, void *tint, const float *s, int sn, float *d, int dn);
+typedef void (mupdf_tint_drop_fn)(fz_context *ctx, void *tint);
+
+/* MuPDF has no public constructor for separations with a custom tint transform, so
+ * the `u.separation` fields of fz_colorspace are filled in directly. Their layout
+ * (base, eval, drop, tint) is the one of MuPDF 1.16 up to 1.25, check it again
+ * before extending this range. */
+#if FZ_VERSION_MAJOR == 1 && FZ_VERSION_MINOR >= 16 && FZ_VERSION_MINOR <= 25
+#define MUPDF_HAVE_SEPARATION_LAYOUT 1
+#endif
+
+static void drop_no_tint(fz_context *ctx, void *tint)
+{
+    (void)ctx;
+    (void)tint;
+}
+
+/* On success the colorspace owns `tint` and releases it with `drop`, on failure the caller still owns it */
+fz_colorspace *mupdf_new_separation_colorspace(fz_context *ctx, const char *name, int n, const char **colorants, fz_colorspace *base, mupdf_tint_eval_fn *eval, mupdf_tint_drop_fn *drop, void *tint, mupdf_error_t **errptr)
+{
+    fz_colorspace *cs = NULL;
+    int flags = 0;
+    int i;
+    fz_var(cs);
+    if (n < 1 || n > FZ_MAX_COLORS)
+    {
+        *errptr = mupdf_new_error_from_str("invalid number of colorants");
+        return NULL;
+    }
+    for (i = 0; i < n; i++)
+    {
+        if (!strcmp(colorants[i], "Cyan") || !strcmp(colorants[i], "Magenta") || !strcmp(colorants[i], "Yellow") || !strcmp(colorants[i], "Black"))
+            flags |= FZ_COLORSPACE_HAS_CMYK;
+        else if (strcmp(colorants[i], "None") && strcmp(colorants[i], "All"))
+            flags |= FZ_COLORSPACE_HAS_SPOTS;
+    }
+    if (n > 1)
+    {
+        flags |= FZ_COLORSPACE_IS_DEVICEN;
+    }
+    fz_try(ctx)
+    {
+#ifndef MUPDF_HAVE_SEPARATION_LAYOUT
+        fz_throw(ctx, FZ_ERROR_GENERIC, "custom separation colorspaces are not supported with this MuPDF version");
+#else
+        cs = fz_new_colorspace(ctx, FZ_COLORSPACE_SEPARATION, flags, n, name);
+        /* Dropping a separation always calls its drop function, even before the tint is attached */
+        cs->u.separation.drop = drop_no_tint;
+        for (i = 0; i < n; i++)
+        {
+            fz_colorspace_name_colorant(ctx, cs, i, colorants[i]);
+        }
+        cs->u.separation.base = fz_keep_colorspace(ctx, base);
+        cs->u.separation.eval = eval;
+        cs->u.separation.drop = drop;
+        cs->u.separation.tint = tint;
+#endif
+    }
+    fz_catch(ctx)
+    {
+        fz_drop_colorspace(ctx, cs);
+        cs = NULL;
+        mupdf_save_error(ctx, errptr);
+    }
+    return cs;
+}
+
+fz_default_colorspaces *mupdf_new_default_colorspaces(fz_context *ctx, mupdf_error_t **errptr)
+{
+    fz_default_colorspaces *default_cs = NULL;
+    fz_try(ctx)
+    {
+        default_cs = fz_new_default_colorspaces(ctx);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return default_cs;
+}
+
+fz_default_colorspaces *mupdf_clone_default_colorspaces(fz_context *ctx, fz_default_colorspaces *base, mupdf_error_t **errptr)
+{
+    fz_default_colorspaces *default_cs = NULL;
+    fz_try(ctx)
+    {
+        default_cs = fz_clone_default_colorspaces(ctx, base);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return default_cs;
+}
+
+fz_default_colorspaces *mupdf_document_default_colorspaces(fz_context *ctx, fz_document *doc, mupdf_error_t **errptr)
+{
+    fz_default_colorspaces *default_cs = NULL;
+    fz_var(default_cs);
+    fz_try(ctx)
+    {
+        fz_colorspace *oi = fz_document_output_intent(ctx, doc);
+        default_cs = fz_new_default_colorspaces(ctx);
+        if (oi)
+        {
+            fz_set_default_output_intent(ctx, default_cs, oi);
+        }
+    }
+    fz_catch(ctx)
+    {
+        fz_drop_default_colorspaces(ctx, default_cs);
+        default_cs = NULL;
+        mupdf_save_error(ctx, errptr);
+    }
+    return default_cs;
+}
+
+void mupdf_set_default_colorspace(fz_context *ctx, fz_default_colorspaces *default_cs, fz_colorspace *cs, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        switch (fz_colorspace_type(ctx, cs))
+        {
+        case FZ_COLORSPACE_GRAY:
+            fz_set_default_gray(ctx, default_cs, cs);
+            break;
+        case FZ_COLORSPACE_RGB:
+            fz_set_default_rgb(ctx, default_cs, cs);
+            break;
+        case FZ_COLORSPACE_CMYK:
+            fz_set_default_cmyk(ctx, default_cs, cs);
+            break;
+        default:
+            fz_throw(ctx, FZ_ERROR_GENERIC, "default colorspace must be gray, RGB or CMYK");
+        }
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
+/* Separations */
+void mupdf_separation_equivalent(fz_context *ctx, fz_separations *seps, int idx, fz_colorspace *dst_cs, float *dst_color, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        fz_separation_equivalent(ctx, seps, idx, dst_cs, dst_color, NULL, fz_default_color_params);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
 /* Pixmap */
 fz_pixmap *mupdf_new_pixmap(fz_context *ctx, fz_colorspace *cs, int x, int y, int w, int h, bool alpha, mupdf_error_t **errptr)
 {
@@ -182,6 +402,89 @@ fz_pixmap *mupdf_new_pixmap(fz_context *ctx, fz_colorspace *cs, int x, int y, in
     return pixmap;
 }
 
+static void check_samples(fz_context *ctx, fz_colorspace *cs, int w, int h, bool alpha, int stride, size_t len)
+{
+    int n = fz_colorspace_n(ctx, cs) + alpha;
+    if (w < 0 || h < 0)
+    {
+        fz_throw(ctx, FZ_ERROR_GENERIC, "invalid width or height");
+    }
+    if (stride < w * n)
+    {
+        fz_throw(ctx, FZ_ERROR_GENERIC, "stride %d too small for %d pixels of %d components", stride, w, n);
+    }
+    if (len < (size_t)stride * h)
+    {
+        fz_throw(ctx, FZ_ERROR_GENERIC, "samples too short, need %zu bytes", (size_t)stride * h);
+    }
+}
+
+fz_pixmap *mupdf_new_pixmap_from_samples(fz_context *ctx, fz_colorspace *cs, int x, int y, int w, int h, bool alpha, int stride, const unsigned char *samples, size_t len, mupdf_error_t **errptr)
+{
+    fz_pixmap *pixmap = NULL;
+    fz_try(ctx)
+    {
+        int row;
+        check_samples(ctx, cs, w, h, alpha, stride, len);
+        pixmap = fz_new_pixmap(ctx, cs, w, h, NULL, alpha);
+        pixmap->x = x;
+        pixmap->y = y;
+        for (row = 0; row < h; row++)
+        {
+            memcpy(pixmap->samples + row * pixmap->stride, samples + (size_t)row * stride, pixmap->stride);
+        }
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return pixmap;
+}
+
+fz_pixmap *mupdf_new_pixmap_with_data(fz_context *ctx, fz_colorspace *cs, int x, int y, int w, int h, bool alpha, int stride, unsigned char *samples, size_t len, mupdf_error_t **errptr)
+{
+    fz_pixmap *pixmap = NULL;
+    fz_try(ctx)
+    {
+        check_samples(ctx, cs, w, h, alpha, stride, len);
+        pixmap = fz_new_pixmap_with_data(ctx, cs, w, h, NULL, alpha, stride, samples);
+        pixmap->x = x;
+        pixmap->y = y;
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return pixmap;
+}
+
+/* Give a pixmap wrapping borrowed samples its own copy before the borrow ends.
+ * There is no public way to tell whether anything else still holds the pixmap, so the
+ * copy is always made. If it can't be allocated the pixmap is emptied instead, leaving
+ * other holders with no pixels rather than dangling ones. */
+void mupdf_pixmap_detach_samples(fz_context *ctx, fz_pixmap *pixmap)
+{
+    size_t size;
+    unsigned char *samples;
+    if (pixmap->flags & FZ_PIXMAP_FLAG_FREE_SAMPLES)
+    {
+        return;
+    }
+    size = (size_t)pixmap->stride * pixmap->h;
+    samples = fz_malloc_no_throw(ctx, size);
+    if (samples)
+    {
+        memcpy(samples, pixmap->samples, size);
+        pixmap->flags |= FZ_PIXMAP_FLAG_FREE_SAMPLES;
+    }
+    else
+    {
+        pixmap->w = 0;
+        pixmap->h = 0;
+    }
+    pixmap->samples = samples;
+}
+
 fz_pixmap *mupdf_clone_pixmap(fz_context *ctx, fz_pixmap *self, mupdf_error_t **errptr)
 {
     fz_pixmap *pixmap = NULL;
@@ -267,36 +570,456 @@ void mupdf_tint_pixmap(fz_context *ctx, fz_pixmap *pixmap, int black, int white,
     }
 }
 
+fz_pixmap *mupdf_convert_pixmap(fz_context *ctx, fz_pixmap *pixmap, fz_colorspace *cs, fz_color_params cp, mupdf_error_t **errptr)
+{
+    fz_pixmap *pix = NULL;
+    fz_try(ctx)
+    {
+        pix = fz_convert_pixmap(ctx, pixmap, cs, NULL, NULL, cp, 1);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return pix;
+}
+
+fz_pixmap *mupdf_scale_pixmap(fz_context *ctx, fz_pixmap *pixmap, float w, float h, const fz_irect *clip, mupdf_error_t **errptr)
+{
+    fz_pixmap *pix = NULL;
+    fz_try(ctx)
+    {
+        pix = fz_scale_pixmap(ctx, pixmap, pixmap->x, pixmap->y, w, h, clip);
+        if (!pix)
+        {
+            fz_throw(ctx, FZ_ERROR_GENERIC, "cannot scale pixmap to %g x %g", w, h);
+        }
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return pix;
+}
+
+void mupdf_subsample_pixmap(fz_context *ctx, fz_pixmap *pixmap, int factor, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        fz_subsample_pixmap(ctx, pixmap, factor);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
+fz_pixmap *mupdf_pixmap_set_alpha(fz_context *ctx, fz_pixmap *pixmap, bool alpha, mupdf_error_t **errptr)
+{
+    fz_pixmap *pix = NULL;
+    fz_try(ctx)
+    {
+        int x, y, k;
+        int colors = pixmap->n - pixmap->alpha;
+        /* Process colorants of an additive colorspace, spots are always subtractive */
+        int additive = 0;
+        if (pixmap->colorspace && !fz_colorspace_is_subtractive(ctx, pixmap->colorspace))
+        {
+            additive = colors - pixmap->s;
+        }
+        pix = fz_new_pixmap_with_bbox(ctx, pixmap->colorspace, fz_pixmap_bbox(ctx, pixmap), pixmap->seps, alpha);
+        pix->xres = pixmap->xres;
+        pix->yres = pixmap->yres;
+        for (y = 0; y < pixmap->h; y++)
+        {
+            const unsigned char *s = pixmap->samples + y * pixmap->stride;
+            unsigned char *d = pix->samples + y * pix->stride;
+            for (x = 0; x < pixmap->w; x++)
+            {
+                /* Samples are premultiplied, dropping alpha composites them over white
+                 * paper: the missing coverage is added to additive colorants and
+                 * subtractive colorants are left as they are (no ink). */
+                int blank = (pixmap->alpha && !alpha) ? 255 - s[colors] : 0;
+                for (k = 0; k < colors; k++)
+                {
+                    int v = *s++;
+                    if (k < additive)
+                    {
+                        v = fz_mini(v + blank, 255);
+                    }
+                    *d++ = v;
+                }
+                if (pixmap->alpha)
+                {
+                    s++;
+                }
+                if (alpha)
+                {
+                    *d++ = 255;
+                }
+            }
+        }
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return pix;
+}
+
+void mupdf_premultiply_pixmap(fz_context *ctx, fz_pixmap *pixmap, mupdf_error_t **errptr)
+{
+    int x, y, k;
+    int colors = pixmap->n - 1;
+    if (!pixmap->alpha)
+    {
+        return;
+    }
+    for (y = 0; y < pixmap->h; y++)
+    {
+        unsigned char *s = pixmap->samples + y * pixmap->stride;
+        for (x = 0; x < pixmap->w; x++, s += pixmap->n)
+        {
+            int a = s[colors];
+            for (k = 0; k < colors; k++)
+            {
+                s[k] = fz_mul255(s[k], a);
+            }
+        }
+    }
+}
+
+void mupdf_unpremultiply_pixmap(fz_context *ctx, fz_pixmap *pixmap, mupdf_error_t **errptr)
+{
+    int x, y, k;
+    int colors = pixmap->n - 1;
+    if (!pixmap->alpha)
+    {
+        return;
+    }
+    for (y = 0; y < pixmap->h; y++)
+    {
+        unsigned char *s = pixmap->samples + y * pixmap->stride;
+        for (x = 0; x < pixmap->w; x++, s += pixmap->n)
+        {
+            int a = s[colors];
+            if (a == 0 || a == 255)
+            {
+                continue;
+            }
+            for (k = 0; k < colors; k++)
+            {
+                s[k] = fz_mini(255, (s[k] * 255 + a / 2) / a);
+            }
+        }
+    }
+}
+
+void mupdf_copy_pixmap_rect(fz_context *ctx, fz_pixmap *dst, fz_pixmap *src, fz_irect rect, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        fz_copy_pixmap_rect(ctx, dst, src, rect, NULL);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
+void mupdf_composite_pixmap(fz_context *ctx, fz_pixmap *dst, fz_pixmap *src, float opacity, mupdf_error_t **errptr)
+{
+    int x, y, k;
+    int colors = dst->n - dst->alpha;
+    int global = fz_clampi(opacity * 255 + 0.5f, 0, 255);
+    fz_irect bbox;
+    if (src->n - src->alpha != colors || src->colorspace != dst->colorspace)
+    {
+        *errptr = mupdf_new_error_from_str("pixmaps must share the same colorspace");
+        return;
+    }
+    bbox = fz_intersect_irect(fz_pixmap_bbox(ctx, dst), fz_pixmap_bbox(ctx, src));
+    if (fz_is_empty_irect(bbox))
+    {
+        return;
+    }
+    for (y = bbox.y0; y < bbox.y1; y++)
+    {
+        const unsigned char *s = src->samples + (y - src->y) * src->stride + (bbox.x0 - src->x) * src->n;
+        unsigned char *d = dst->samples + (y - dst->y) * dst->stride + (bbox.x0 - dst->x) * dst->n;
+        for (x = bbox.x0; x < bbox.x1; x++, s += src->n, d += dst->n)
+        {
+            int sa = fz_mul255(src->alpha ? s[colors] : 255, global);
+            int t = 255 - sa;
+            /* Clamp, colors of a source that isn't premultiplied can exceed its alpha */
+            for (k = 0; k < colors; k++)
+            {
+                d[k] = fz_mini(fz_mul255(s[k], global) + fz_mul255(d[k], t), 255);
+            }
+            if (dst->alpha)
+            {
+                d[colors] = fz_mini(sa + fz_mul255(d[colors], t), 255);
+            }
+        }
+    }
+}
+
+#if FZ_VERSION_MAJOR > 1 || (FZ_VERSION_MAJOR == 1 && FZ_VERSION_MINOR >= 24)
+#define MUPDF_HAVE_DESKEW 1
+#endif
+
+float mupdf_detect_skew(fz_context *ctx, fz_pixmap *pixmap, mupdf_error_t **errptr)
+{
+    float degrees = 0;
+    fz_try(ctx)
+    {
+#ifdef MUPDF_HAVE_DESKEW
+        degrees = (float)fz_detect_skew(ctx, pixmap);
+#else
+        fz_throw(ctx, FZ_ERROR_GENERIC, "skew detection requires MuPDF 1.24");
+#endif
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return degrees;
+}
+
+fz_pixmap *mupdf_deskew_pixmap(fz_context *ctx, fz_pixmap *pixmap, float degrees, int border, mupdf_error_t **errptr)
+{
+    fz_pixmap *pix = NULL;
+    fz_try(ctx)
+    {
+#ifdef MUPDF_HAVE_DESKEW
+        pix = fz_deskew_pixmap(ctx, pixmap, degrees, border);
+#else
+        fz_throw(ctx, FZ_ERROR_GENERIC, "deskewing requires MuPDF 1.24");
+#endif
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return pix;
+}
+
+bool mupdf_detect_document(fz_context *ctx, fz_pixmap *pixmap, fz_quad *quad, mupdf_error_t **errptr)
+{
+    bool found = false;
+    fz_try(ctx)
+    {
+#ifdef MUPDF_HAVE_DESKEW
+        found = fz_detect_document(ctx, quad, pixmap);
+#else
+        fz_throw(ctx, FZ_ERROR_GENERIC, "document detection requires MuPDF 1.24");
+#endif
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return found;
+}
+
+fz_pixmap *mupdf_warp_pixmap(fz_context *ctx, fz_pixmap *pixmap, fz_quad quad, int width, int height, mupdf_error_t **errptr)
+{
+    fz_pixmap *pix = NULL;
+    fz_try(ctx)
+    {
+#ifdef MUPDF_HAVE_DESKEW
+        pix = fz_warp_pixmap(ctx, pixmap, quad, width, height);
+#else
+        fz_throw(ctx, FZ_ERROR_GENERIC, "warping requires MuPDF 1.24");
+#endif
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return pix;
+}
+
+#define MUPDF_IMAGE_PNG 0
+#define MUPDF_IMAGE_PNM 1
+#define MUPDF_IMAGE_PAM 2
+#define MUPDF_IMAGE_PSD 3
+#define MUPDF_IMAGE_PS 4
+#define MUPDF_IMAGE_JPEG 5
+#define MUPDF_IMAGE_PBM 6
+#define MUPDF_IMAGE_PKM 7
+#define MUPDF_IMAGE_PCLM 8
+#define MUPDF_IMAGE_PWG 9
+#define MUPDF_IMAGE_JPX 10
+
+static void write_bitmap(fz_context *ctx, fz_output *out, fz_pixmap *pixmap, fz_colorspace *cs)
+{
+    fz_pixmap *converted = NULL;
+    fz_bitmap *bitmap = NULL;
+    fz_var(converted);
+    fz_var(bitmap);
+    fz_try(ctx)
+    {
+        if (pixmap->colorspace != cs || pixmap->alpha)
+        {
+            converted = fz_convert_pixmap(ctx, pixmap, cs, NULL, NULL, fz_default_color_params, 0);
+            pixmap = converted;
+        }
+        bitmap = fz_new_bitmap_from_pixmap(ctx, pixmap, NULL);
+        if (cs == fz_device_gray(ctx))
+        {
+            fz_write_bitmap_as_pbm(ctx, out, bitmap);
+        }
+        else
+        {
+            fz_write_bitmap_as_pkm(ctx, out, bitmap);
+        }
+    }
+    fz_always(ctx)
+    {
+        fz_drop_bitmap(ctx, bitmap);
+        fz_drop_pixmap(ctx, converted);
+    }
+    fz_catch(ctx)
+    {
+        fz_rethrow(ctx);
+    }
+}
+
+static void write_pixmap(fz_context *ctx, fz_output *out, fz_pixmap *pixmap, int format, int quality)
+{
+    switch (format)
+    {
+    case MUPDF_IMAGE_PNG:
+        fz_write_pixmap_as_png(ctx, out, pixmap);
+        break;
+    case MUPDF_IMAGE_PNM:
+        fz_write_pixmap_as_pnm(ctx, out, pixmap);
+        break;
+    case MUPDF_IMAGE_PAM:
+        fz_write_pixmap_as_pam(ctx, out, pixmap);
+        break;
+    case MUPDF_IMAGE_PSD: // Adobe Photoshop Document
+        fz_write_pixmap_as_psd(ctx, out, pixmap);
+        break;
+    case MUPDF_IMAGE_PS: // Postscript format
+        fz_write_pixmap_as_ps(ctx, out, pixmap);
+        break;
+    case MUPDF_IMAGE_JPEG:
+        fz_write_pixmap_as_jpeg(ctx, out, pixmap, quality, 0);
+        break;
+    case MUPDF_IMAGE_PBM:
+        write_bitmap(ctx, out, pixmap, fz_device_gray(ctx));
+        break;
+    case MUPDF_IMAGE_PKM:
+        write_bitmap(ctx, out, pixmap, fz_device_cmyk(ctx));
+        break;
+    case MUPDF_IMAGE_PCLM:
+    {
+        fz_pclm_options opts;
+        fz_parse_pclm_options(ctx, &opts, "");
+        fz_write_pixmap_as_pclm(ctx, out, pixmap, &opts);
+        break;
+    }
+    case MUPDF_IMAGE_PWG:
+        fz_write_pixmap_as_pwg(ctx, out, pixmap, NULL);
+        break;
+    case MUPDF_IMAGE_JPX:
+        fz_write_pixmap_as_jpx(ctx, out, pixmap, quality);
+        break;
+    default:
+        fz_throw(ctx, FZ_ERROR_GENERIC, "unknown image format");
+    }
+}
+
 void mupdf_save_pixmap_as(fz_context *ctx, fz_pixmap *pixmap, const char *filename, int format, mupdf_error_t **errptr)
 {
+    fz_output *out = NULL;
+    fz_var(out);
     fz_try(ctx)
     {
-        switch (format)
+        out = fz_new_output_with_path(ctx, filename, 0);
+        write_pixmap(ctx, out, pixmap, format, 90);
+        fz_close_output(ctx, out);
+    }
+    fz_always(ctx)
+    {
+        fz_drop_output(ctx, out);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
+void mupdf_write_pixmap(fz_context *ctx, fz_output *out, fz_pixmap *pixmap, int format, int quality, int xres, int yres, mupdf_error_t **errptr)
+{
+    fz_pixmap *view = NULL;
+    fz_var(view);
+    fz_try(ctx)
+    {
+        if (xres > 0 && yres > 0)
         {
-        case (0):
-            fz_save_pixmap_as_png(ctx, pixmap, filename);
-            break;
-        case (1):
-            fz_save_pixmap_as_pnm(ctx, pixmap, filename);
-            break;
-        case (2):
-            fz_save_pixmap_as_pam(ctx, pixmap, filename);
-            break;
-        case (3): // Adobe Photoshop Document
-            fz_save_pixmap_as_psd(ctx, pixmap, filename);
-            break;
-        case (4): // Postscript format
-            fz_save_pixmap_as_ps(ctx, pixmap, (char *)filename, 0);
-            break;
-        default:
-            fz_save_pixmap_as_png(ctx, pixmap, filename);
-            break;
+            /* Never touch the caller's pixmap, write through a view sharing its samples */
+            view = fz_new_pixmap_with_data(ctx, pixmap->colorspace, pixmap->w, pixmap->h, pixmap->seps, pixmap->alpha, pixmap->stride, pixmap->samples);
+            view->x = pixmap->x;
+            view->y = pixmap->y;
+            fz_set_pixmap_resolution(ctx, view, xres, yres);
+            write_pixmap(ctx, out, view, format, quality);
+        }
+        else
+        {
+            write_pixmap(ctx, out, pixmap, format, quality);
         }
     }
+    fz_always(ctx)
+    {
+        fz_drop_pixmap(ctx, view);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
+fz_buffer *mupdf_pixmap_to_data_uri(fz_context *ctx, fz_pixmap *pixmap, int format, int quality, const char *mime, mupdf_error_t **errptr)
+{
+    fz_output *out = NULL;
+    fz_buffer *image = NULL;
+    fz_buffer *buf = NULL;
+    fz_var(out);
+    fz_var(image);
+    fz_var(buf);
+    fz_try(ctx)
+    {
+        unsigned char *data;
+        size_t len;
+        image = fz_new_buffer(ctx, 1024);
+        out = fz_new_output_with_buffer(ctx, image);
+        write_pixmap(ctx, out, pixmap, format, quality);
+        fz_close_output(ctx, out);
+        fz_drop_output(ctx, out);
+        out = NULL;
+
+        len = fz_buffer_storage(ctx, image, &data);
+        buf = fz_new_buffer(ctx, len * 4 / 3 + 64);
+        out = fz_new_output_with_buffer(ctx, buf);
+        fz_write_printf(ctx, out, "data:%s;base64,", mime);
+        fz_write_base64(ctx, out, data, len, 0);
+        fz_close_output(ctx, out);
+    }
+    fz_always(ctx)
+    {
+        fz_drop_output(ctx, out);
+        fz_drop_buffer(ctx, image);
+    }
     fz_catch(ctx)
     {
+        fz_drop_buffer(ctx, buf);
+        buf = NULL;
         mupdf_save_error(ctx, errptr);
     }
+    return buf;
 }
 
 fz_buffer *mupdf_pixmap_get_image_data(fz_context *ctx, fz_pixmap *pixmap, int format, mupdf_error_t **errptr)
@@ -310,27 +1033,8 @@ fz_buffer *mupdf_pixmap_get_image_data(fz_context *ctx, fz_pixmap *pixmap, int f
         size_t size = fz_pixmap_stride(ctx, pixmap) * pixmap->h;
         buf = fz_new_buffer(ctx, size);
         out = fz_new_output_with_buffer(ctx, buf);
-        switch (format)
-        {
-        case (0):
-            fz_write_pixmap_as_png(ctx, out, pixmap);
-            break;
-        case (1):
-            fz_write_pixmap_as_pnm(ctx, out, pixmap);
-            break;
-        case (2):
-            fz_write_pixmap_as_pam(ctx, out, pixmap);
-            break;
-        case (3): // Adobe Photoshop Document
-            fz_write_pixmap_as_psd(ctx, out, pixmap);
-            break;
-        case (4): // Postscript format
-            fz_write_pixmap_as_ps(ctx, out, pixmap);
-            break;
-        default:
-            fz_write_pixmap_as_png(ctx, out, pixmap);
-            break;
-        }
+        write_pixmap(ctx, out, pixmap, format, 90);
+        fz_close_output(ctx, out);
     }
     fz_always(ctx)
     {
@@ -338,6 +1042,8 @@ fz_buffer *mupdf_pixmap_get_image_data(fz_context *ctx, fz_pixmap *pixmap, int f
     }
     fz_catch(ctx)
     {
+        fz_drop_buffer(ctx, buf);
+        buf = NULL;
         mupdf_save_error(ctx, errptr);
     }
     return buf;
@@ -740,7 +1446,207 @@ fz_pixmap *mupdf_page_to_pixmap(fz_context *ctx, fz_page *page, fz_matrix ctm, f
     {
         mupdf_save_error(ctx, errptr);
     }
-    return pixmap;
+    return pixmap;
+}
+
+static fz_device *new_filter_device(fz_context *ctx, fz_device *target, int skip, const fz_color_params *cp, fz_default_colorspaces *default_cs);
+
+fz_pixmap *mupdf_page_to_pixmap_with_options(fz_context *ctx, fz_page *page, fz_matrix ctm, fz_colorspace *cs, bool alpha, fz_irect clip, const float *background, fz_separations *seps, bool annots, bool widgets, int aa_level, const fz_color_params *cp, fz_default_colorspaces *default_cs, fz_colorspace *proof, fz_cookie *cookie, mupdf_error_t **errptr)
+{
+    fz_pixmap *pix = NULL;
+    fz_device *draw = NULL;
+    fz_device *dev = NULL;
+    int text_aa = fz_text_aa_level(ctx);
+    int graphics_aa = fz_graphics_aa_level(ctx);
+    fz_var(pix);
+    fz_var(draw);
+    fz_var(dev);
+    fz_try(ctx)
+    {
+        fz_irect bbox = fz_round_rect(fz_transform_rect(fz_bound_page(ctx, page), ctm));
+        bbox = fz_intersect_irect(bbox, clip);
+        if (fz_is_empty_irect(bbox))
+        {
+            fz_throw(ctx, FZ_ERROR_GENERIC, "clip does not intersect the page");
+        }
+        pix = fz_new_pixmap_with_bbox(ctx, cs, bbox, seps, alpha);
+        if (background)
+        {
+            fz_fill_pixmap_with_color(ctx, pix, fz_device_rgb(ctx), (float *)background, fz_default_color_params);
+        }
+        else if (alpha)
+        {
+            fz_clear_pixmap(ctx, pix);
+        }
+        else
+        {
+            fz_clear_pixmap_with_value(ctx, pix, 0xFF);
+        }
+        if (aa_level >= 0)
+        {
+            fz_set_aa_level(ctx, aa_level);
+        }
+        draw = fz_new_draw_device_with_proof(ctx, fz_identity, pix, proof);
+        if (default_cs)
+        {
+            /* Non-PDF pages never set default colorspaces themselves */
+            fz_set_default_colorspaces(ctx, draw, default_cs);
+        }
+        dev = (cp || default_cs) ? new_filter_device(ctx, draw, 0, cp, default_cs) : fz_keep_device(ctx, draw);
+        fz_run_page_contents(ctx, page, dev, ctm, cookie);
+        if (annots)
+        {
+            fz_run_page_annots(ctx, page, dev, ctm, cookie);
+        }
+        if (widgets)
+        {
+            fz_run_page_widgets(ctx, page, dev, ctm, cookie);
+        }
+        fz_close_device(ctx, dev);
+        fz_close_device(ctx, draw);
+    }
+    fz_always(ctx)
+    {
+        fz_set_text_aa_level(ctx, text_aa);
+        fz_set_graphics_aa_level(ctx, graphics_aa);
+        fz_drop_device(ctx, dev);
+        fz_drop_device(ctx, draw);
+    }
+    fz_catch(ctx)
+    {
+        fz_drop_pixmap(ctx, pix);
+        pix = NULL;
+        mupdf_save_error(ctx, errptr);
+    }
+    return pix;
+}
+
+fz_pixmap *mupdf_page_to_pixmap_simulate_overprint(fz_context *ctx, fz_page *page, fz_matrix ctm, fz_separations *seps, mupdf_error_t **errptr)
+{
+    fz_pixmap *pix = NULL;
+    fz_pixmap *rgb = NULL;
+    fz_var(pix);
+    fz_var(rgb);
+    fz_try(ctx)
+    {
+        pix = fz_new_pixmap_from_page_with_separations(ctx, page, ctm, fz_device_cmyk(ctx), seps, 0);
+        rgb = fz_clone_pixmap_area_with_different_seps(ctx, pix, NULL, fz_device_rgb(ctx), NULL, fz_default_color_params, NULL);
+    }
+    fz_always(ctx)
+    {
+        fz_drop_pixmap(ctx, pix);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return rgb;
+}
+
+/* Keep in sync with `BandFormat` in src/band_writer.rs */
+#define MUPDF_BAND_PNG 0
+#define MUPDF_BAND_PNM 1
+#define MUPDF_BAND_PAM 2
+#define MUPDF_BAND_PCL 3
+#define MUPDF_BAND_PCLM 4
+#define MUPDF_BAND_PWG 5
+
+static fz_band_writer *new_band_writer(fz_context *ctx, fz_output *out, int format)
+{
+    switch (format)
+    {
+    case MUPDF_BAND_PNG:
+        return fz_new_png_band_writer(ctx, out);
+    case MUPDF_BAND_PNM:
+        return fz_new_pnm_band_writer(ctx, out);
+    case MUPDF_BAND_PAM:
+        return fz_new_pam_band_writer(ctx, out);
+    case MUPDF_BAND_PCL:
+    {
+        fz_pcl_options opts;
+        fz_pcl_preset(ctx, &opts, "generic");
+        return fz_new_color_pcl_band_writer(ctx, out, &opts);
+    }
+    case MUPDF_BAND_PCLM:
+    {
+        fz_pclm_options opts;
+        fz_parse_pclm_options(ctx, &opts, "");
+        return fz_new_pclm_band_writer(ctx, out, &opts);
+    }
+    case MUPDF_BAND_PWG:
+    {
+        fz_pwg_options opts;
+        memset(&opts, 0, sizeof(opts));
+        fz_write_pwg_file_header(ctx, out);
+        return fz_new_pwg_band_writer(ctx, out, &opts);
+    }
+    default:
+        fz_throw(ctx, FZ_ERROR_GENERIC, "unknown band format");
+    }
+}
+
+void mupdf_page_write_bands(fz_context *ctx, fz_page *page, fz_output *out, int format, fz_matrix ctm, fz_colorspace *cs, bool alpha, int band_height, mupdf_error_t **errptr)
+{
+    fz_display_list *list = NULL;
+    fz_band_writer *writer = NULL;
+    fz_pixmap *band = NULL;
+    fz_device *dev = NULL;
+    fz_var(list);
+    fz_var(writer);
+    fz_var(band);
+    fz_var(dev);
+    fz_try(ctx)
+    {
+        fz_irect bbox = fz_round_rect(fz_transform_rect(fz_bound_page(ctx, page), ctm));
+        int w = bbox.x1 - bbox.x0;
+        int h = bbox.y1 - bbox.y0;
+        int res = (int)(fz_matrix_expansion(ctm) * 72 + 0.5f);
+        int y;
+        if (w <= 0 || h <= 0)
+        {
+            fz_throw(ctx, FZ_ERROR_GENERIC, "page is empty");
+        }
+        if (band_height <= 0 || band_height > h)
+        {
+            band_height = h;
+        }
+        list = fz_new_display_list_from_page(ctx, page);
+        writer = new_band_writer(ctx, out, format);
+        fz_write_header(ctx, writer, w, h, fz_colorspace_n(ctx, cs) + alpha, alpha, res, res, 0, cs, NULL);
+        band = fz_new_pixmap(ctx, cs, w, band_height, NULL, alpha);
+        fz_set_pixmap_resolution(ctx, band, res, res);
+        for (y = 0; y < h; y += band_height)
+        {
+            band->x = bbox.x0;
+            band->y = bbox.y0 + y;
+            if (alpha)
+            {
+                fz_clear_pixmap(ctx, band);
+            }
+            else
+            {
+                fz_clear_pixmap_with_value(ctx, band, 0xFF);
+            }
+            dev = fz_new_draw_device(ctx, fz_identity, band);
+            fz_run_display_list(ctx, list, dev, ctm, fz_rect_from_irect(fz_pixmap_bbox(ctx, band)), NULL);
+            fz_close_device(ctx, dev);
+            fz_drop_device(ctx, dev);
+            dev = NULL;
+            fz_write_band(ctx, writer, band->stride, fz_mini(band_height, h - y), band->samples);
+        }
+        fz_close_band_writer(ctx, writer);
+    }
+    fz_always(ctx)
+    {
+        fz_drop_device(ctx, dev);
+        fz_drop_pixmap(ctx, band);
+        fz_drop_band_writer(ctx, writer);
+        fz_drop_display_list(ctx, list);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
 }
 
 fz_buffer *mupdf_page_to_svg(fz_context *ctx, fz_page *page, fz_matrix ctm, fz_cookie *cookie, mupdf_error_t **errptr)
@@ -1017,6 +1923,18 @@ fz_link *mupdf_load_links(fz_context *ctx, fz_page *page, mupdf_error_t **errptr
     return link;
 }
 
+void mupdf_stext_char_style(const fz_stext_char *ch, int *bidi, int *flags, unsigned int *argb)
+{
+    *bidi = ch->bidi;
+#if FZ_VERSION_MAJOR > 1 || (FZ_VERSION_MAJOR == 1 && FZ_VERSION_MINOR >= 24)
+    *flags = ch->flags;
+    *argb = ch->argb;
+#else
+    *flags = 0;
+    *argb = 0xff000000 | (unsigned int)ch->color;
+#endif
+}
+
 fz_buffer *mupdf_stext_page_to_text(fz_context *ctx, fz_stext_page *page, mupdf_error_t **errptr)
 {
     fz_buffer *buf = NULL;
@@ -1082,6 +2000,77 @@ fz_quad *mupdf_search_page(fz_context *ctx, fz_page *page, const char *needle, c
     return result;
 }
 
+fz_quad mupdf_snap_selection(fz_context *ctx, fz_stext_page *page, fz_point *a, fz_point *b, int mode, mupdf_error_t **errptr)
+{
+    fz_quad quad = { 0 };
+    fz_try(ctx)
+    {
+        quad = fz_snap_selection(ctx, page, a, b, mode);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return quad;
+}
+
+fz_quad *mupdf_highlight_selection(fz_context *ctx, fz_stext_page *page, fz_point a, fz_point b, int mode, int *count, mupdf_error_t **errptr)
+{
+    fz_quad *quads = NULL;
+    fz_var(quads);
+    fz_try(ctx)
+    {
+        int max = 64;
+        fz_snap_selection(ctx, page, &a, &b, mode);
+        while (1)
+        {
+            quads = fz_realloc_array(ctx, quads, max, fz_quad);
+            *count = fz_highlight_selection(ctx, page, a, b, quads, max);
+            if (*count < max)
+            {
+                break;
+            }
+            max *= 2;
+        }
+    }
+    fz_catch(ctx)
+    {
+        fz_free(ctx, quads);
+        quads = NULL;
+        mupdf_save_error(ctx, errptr);
+    }
+    return quads;
+}
+
+char *mupdf_copy_selection(fz_context *ctx, fz_stext_page *page, fz_point a, fz_point b, int mode, mupdf_error_t **errptr)
+{
+    char *text = NULL;
+    fz_try(ctx)
+    {
+        fz_snap_selection(ctx, page, &a, &b, mode);
+        text = fz_copy_selection(ctx, page, a, b, 0);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return text;
+}
+
+char *mupdf_copy_rectangle(fz_context *ctx, fz_stext_page *page, fz_rect area, mupdf_error_t **errptr)
+{
+    char *text = NULL;
+    fz_try(ctx)
+    {
+        text = fz_copy_rectangle(ctx, page, area, 0);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return text;
+}
+
 fz_quad *mupdf_search_stext_page(fz_context *ctx, fz_stext_page *page, const char *needle, const int hit_max, int *hit_count, mupdf_error_t **errptr)
 {
     fz_quad *result = NULL;
@@ -1940,6 +2929,33 @@ fz_buffer *mupdf_buffer_from_base64(fz_context *ctx, const char *s, mupdf_error_
     return buf;
 }
 
+/* Output */
+fz_output *mupdf_new_output(fz_context *ctx, void *state, fz_output_write_fn *write, mupdf_error_t **errptr)
+{
+    fz_output *out = NULL;
+    fz_try(ctx)
+    {
+        out = fz_new_output(ctx, 8192, state, write, NULL, NULL);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return out;
+}
+
+void mupdf_close_output(fz_context *ctx, fz_output *out, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        fz_close_output(ctx, out);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
 /* Document */
 fz_document *mupdf_open_document(fz_context *ctx, const char *filename, mupdf_error_t **errptr)
 {
@@ -2642,6 +3658,281 @@ fz_device *mupdf_new_display_list_device(fz_context *ctx, fz_display_list *list,
     return device;
 }
 
+fz_device *mupdf_new_bbox_device(fz_context *ctx, fz_rect *rect, mupdf_error_t **errptr)
+{
+    fz_device *device = NULL;
+    fz_try(ctx)
+    {
+        device = fz_new_bbox_device(ctx, rect);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return device;
+}
+
+fz_device *mupdf_new_test_device(fz_context *ctx, int *is_color, float threshold, int options, mupdf_error_t **errptr)
+{
+    fz_device *device = NULL;
+    fz_try(ctx)
+    {
+        device = fz_new_test_device(ctx, is_color, threshold, options, NULL);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return device;
+}
+
+fz_device *mupdf_new_derived_device(fz_context *ctx, int size, mupdf_error_t **errptr)
+{
+    fz_device *device = NULL;
+    fz_try(ctx)
+    {
+        device = fz_new_device_of_size(ctx, size);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return device;
+}
+
+/* Keep in sync with `DeviceFilter` in src/device/filter.rs */
+#define MUPDF_FILTER_TEXT 1
+#define MUPDF_FILTER_IMAGES 2
+#define MUPDF_FILTER_VECTORS 4
+#define MUPDF_FILTER_IMAGE_MASKS 8
+
+typedef struct
+{
+    fz_device super;
+    fz_device *target;
+    int skip;
+    int override_cp;
+    fz_color_params cp;
+    fz_default_colorspaces *default_cs;
+} mupdf_filter_device;
+
+static fz_color_params filter_cp(mupdf_filter_device *dev, fz_color_params cp)
+{
+    return dev->override_cp ? dev->cp : cp;
+}
+
+static void filter_drop_device(fz_context *ctx, fz_device *dev_)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_drop_default_colorspaces(ctx, dev->default_cs);
+    fz_drop_device(ctx, dev->target);
+}
+
+static void filter_fill_path(fz_context *ctx, fz_device *dev_, const fz_path *path, int even_odd, fz_matrix ctm, fz_colorspace *cs, const float *color, float alpha, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_VECTORS))
+        fz_fill_path(ctx, dev->target, path, even_odd, ctm, cs, color, alpha, filter_cp(dev, cp));
+}
+
+static void filter_stroke_path(fz_context *ctx, fz_device *dev_, const fz_path *path, const fz_stroke_state *stroke, fz_matrix ctm, fz_colorspace *cs, const float *color, float alpha, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_VECTORS))
+        fz_stroke_path(ctx, dev->target, path, stroke, ctm, cs, color, alpha, filter_cp(dev, cp));
+}
+
+static void filter_clip_path(fz_context *ctx, fz_device *dev_, const fz_path *path, int even_odd, fz_matrix ctm, fz_rect scissor)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_clip_path(ctx, dev->target, path, even_odd, ctm, scissor);
+}
+
+static void filter_clip_stroke_path(fz_context *ctx, fz_device *dev_, const fz_path *path, const fz_stroke_state *stroke, fz_matrix ctm, fz_rect scissor)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_clip_stroke_path(ctx, dev->target, path, stroke, ctm, scissor);
+}
+
+static void filter_fill_text(fz_context *ctx, fz_device *dev_, const fz_text *text, fz_matrix ctm, fz_colorspace *cs, const float *color, float alpha, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_TEXT))
+        fz_fill_text(ctx, dev->target, text, ctm, cs, color, alpha, filter_cp(dev, cp));
+}
+
+static void filter_stroke_text(fz_context *ctx, fz_device *dev_, const fz_text *text, const fz_stroke_state *stroke, fz_matrix ctm, fz_colorspace *cs, const float *color, float alpha, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_TEXT))
+        fz_stroke_text(ctx, dev->target, text, stroke, ctm, cs, color, alpha, filter_cp(dev, cp));
+}
+
+static void filter_clip_text(fz_context *ctx, fz_device *dev_, const fz_text *text, fz_matrix ctm, fz_rect scissor)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_clip_text(ctx, dev->target, text, ctm, scissor);
+}
+
+static void filter_clip_stroke_text(fz_context *ctx, fz_device *dev_, const fz_text *text, const fz_stroke_state *stroke, fz_matrix ctm, fz_rect scissor)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_clip_stroke_text(ctx, dev->target, text, stroke, ctm, scissor);
+}
+
+static void filter_ignore_text(fz_context *ctx, fz_device *dev_, const fz_text *text, fz_matrix ctm)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_TEXT))
+        fz_ignore_text(ctx, dev->target, text, ctm);
+}
+
+static void filter_fill_shade(fz_context *ctx, fz_device *dev_, fz_shade *shade, fz_matrix ctm, float alpha, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_VECTORS))
+        fz_fill_shade(ctx, dev->target, shade, ctm, alpha, filter_cp(dev, cp));
+}
+
+static void filter_fill_image(fz_context *ctx, fz_device *dev_, fz_image *image, fz_matrix ctm, float alpha, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_IMAGES))
+        fz_fill_image(ctx, dev->target, image, ctm, alpha, filter_cp(dev, cp));
+}
+
+static void filter_fill_image_mask(fz_context *ctx, fz_device *dev_, fz_image *image, fz_matrix ctm, fz_colorspace *cs, const float *color, float alpha, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    if (!(dev->skip & MUPDF_FILTER_IMAGE_MASKS))
+        fz_fill_image_mask(ctx, dev->target, image, ctm, cs, color, alpha, filter_cp(dev, cp));
+}
+
+static void filter_clip_image_mask(fz_context *ctx, fz_device *dev_, fz_image *image, fz_matrix ctm, fz_rect scissor)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_clip_image_mask(ctx, dev->target, image, ctm, scissor);
+}
+
+static void filter_pop_clip(fz_context *ctx, fz_device *dev_)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_pop_clip(ctx, dev->target);
+}
+
+static void filter_begin_mask(fz_context *ctx, fz_device *dev_, fz_rect area, int luminosity, fz_colorspace *cs, const float *bc, fz_color_params cp)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_begin_mask(ctx, dev->target, area, luminosity, cs, bc, filter_cp(dev, cp));
+}
+
+static void filter_end_mask(fz_context *ctx, fz_device *dev_, fz_function *tr)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_end_mask_tr(ctx, dev->target, tr);
+}
+
+static void filter_begin_group(fz_context *ctx, fz_device *dev_, fz_rect area, fz_colorspace *cs, int isolated, int knockout, int blendmode, float alpha)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_begin_group(ctx, dev->target, area, cs, isolated, knockout, blendmode, alpha);
+}
+
+static void filter_end_group(fz_context *ctx, fz_device *dev_)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_end_group(ctx, dev->target);
+}
+
+static int filter_begin_tile(fz_context *ctx, fz_device *dev_, fz_rect area, fz_rect view, float xstep, float ystep, fz_matrix ctm, int id, int doc_id)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    return fz_begin_tile_tid(ctx, dev->target, area, view, xstep, ystep, ctm, id, doc_id);
+}
+
+static void filter_end_tile(fz_context *ctx, fz_device *dev_)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_end_tile(ctx, dev->target);
+}
+
+static void filter_render_flags(fz_context *ctx, fz_device *dev_, int set, int clear)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_render_flags(ctx, dev->target, set, clear);
+}
+
+static void filter_set_default_colorspaces(fz_context *ctx, fz_device *dev_, fz_default_colorspaces *default_cs)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_set_default_colorspaces(ctx, dev->target, dev->default_cs ? dev->default_cs : default_cs);
+}
+
+static void filter_begin_layer(fz_context *ctx, fz_device *dev_, const char *name)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_begin_layer(ctx, dev->target, name);
+}
+
+static void filter_end_layer(fz_context *ctx, fz_device *dev_)
+{
+    mupdf_filter_device *dev = (mupdf_filter_device *)dev_;
+    fz_end_layer(ctx, dev->target);
+}
+
+static fz_device *new_filter_device(fz_context *ctx, fz_device *target, int skip, const fz_color_params *cp, fz_default_colorspaces *default_cs)
+{
+    mupdf_filter_device *device = fz_new_derived_device(ctx, mupdf_filter_device);
+    device->super.drop_device = filter_drop_device;
+    device->super.fill_path = filter_fill_path;
+    device->super.stroke_path = filter_stroke_path;
+    device->super.clip_path = filter_clip_path;
+    device->super.clip_stroke_path = filter_clip_stroke_path;
+    device->super.fill_text = filter_fill_text;
+    device->super.stroke_text = filter_stroke_text;
+    device->super.clip_text = filter_clip_text;
+    device->super.clip_stroke_text = filter_clip_stroke_text;
+    device->super.ignore_text = filter_ignore_text;
+    device->super.fill_shade = filter_fill_shade;
+    device->super.fill_image = filter_fill_image;
+    device->super.fill_image_mask = filter_fill_image_mask;
+    device->super.clip_image_mask = filter_clip_image_mask;
+    device->super.pop_clip = filter_pop_clip;
+    device->super.begin_mask = filter_begin_mask;
+    device->super.end_mask = filter_end_mask;
+    device->super.begin_group = filter_begin_group;
+    device->super.end_group = filter_end_group;
+    device->super.begin_tile = filter_begin_tile;
+    device->super.end_tile = filter_end_tile;
+    device->super.render_flags = filter_render_flags;
+    device->super.set_default_colorspaces = filter_set_default_colorspaces;
+    device->super.begin_layer = filter_begin_layer;
+    device->super.end_layer = filter_end_layer;
+    device->target = fz_keep_device(ctx, target);
+    device->skip = skip;
+    if (cp)
+    {
+        device->override_cp = 1;
+        device->cp = *cp;
+    }
+    device->default_cs = fz_keep_default_colorspaces(ctx, default_cs);
+    return (fz_device *)device;
+}
+
+fz_device *mupdf_new_filter_device(fz_context *ctx, fz_device *target, int skip, mupdf_error_t **errptr)
+{
+    fz_device *device = NULL;
+    fz_try(ctx)
+    {
+        device = new_filter_device(ctx, target, skip, NULL, NULL);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return device;
+}
+
 fz_device *mupdf_new_stext_device(fz_context *ctx, fz_stext_page *tp, int flags, mupdf_error_t **errptr)
 {
     fz_device *device = NULL;
@@ -2924,6 +4215,18 @@ void mupdf_end_tile(fz_context *ctx, fz_device *device, mupdf_error_t **errptr)
     }
 }
 
+void mupdf_close_device(fz_context *ctx, fz_device *device, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        fz_close_device(ctx, device);
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
+
 /* PdfPage */
 pdf_annot *mupdf_pdf_create_annot(fz_context *ctx, pdf_page *page, int subtype, mupdf_error_t **errptr)
 {
@@ -3192,3 +4495,83 @@ fz_bitmap *mupdf_new_bitmap_from_pixmap(fz_context *ctx, fz_pixmap *pixmap, mupd
     }
     return bitmap;
 }
+
+fz_bitmap *mupdf_new_bitmap_from_pixmap_with_thresholds(fz_context *ctx, fz_pixmap *pixmap, const unsigned char *thresholds, int tw, int th, mupdf_error_t **errptr)
+{
+    fz_bitmap *bitmap = NULL;
+    int colors = pixmap->n - pixmap->alpha;
+    if (colors != 1 && colors != 4)
+    {
+        *errptr = mupdf_new_error_from_str("pixmap must be gray or CMYK");
+        return NULL;
+    }
+    if (tw <= 0 || th <= 0)
+    {
+        *errptr = mupdf_new_error_from_str("invalid threshold matrix size");
+        return NULL;
+    }
+    fz_try(ctx)
+    {
+        int x, y, k;
+        bitmap = fz_new_bitmap(ctx, pixmap->w, pixmap->h, colors, pixmap->xres, pixmap->yres);
+        fz_clear_bitmap(ctx, bitmap);
+        for (y = 0; y < pixmap->h; y++)
+        {
+            const unsigned char *s = pixmap->samples + y * pixmap->stride;
+            const unsigned char *row = thresholds + ((unsigned)(y + pixmap->y) % th) * tw;
+            unsigned char *d = bitmap->samples + y * bitmap->stride;
+            int bit = 0;
+            for (x = 0; x < pixmap->w; x++, s += pixmap->n)
+            {
+                int t = row[(unsigned)(x + pixmap->x) % tw];
+                for (k = 0; k < colors; k++, bit++)
+                {
+                    /* Gray is additive, so ink is the absence of light */
+                    int ink = colors == 1 ? 255 - s[k] : s[k];
+                    if (ink > t)
+                    {
+                        d[bit >> 3] |= 0x80 >> (bit & 7);
+                    }
+                }
+            }
+        }
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+    return bitmap;
+}
+
+#define MUPDF_BITMAP_PBM 0
+#define MUPDF_BITMAP_PKM 1
+#define MUPDF_BITMAP_PCL 2
+
+void mupdf_write_bitmap(fz_context *ctx, fz_output *out, fz_bitmap *bitmap, int format, mupdf_error_t **errptr)
+{
+    fz_try(ctx)
+    {
+        switch (format)
+        {
+        case MUPDF_BITMAP_PBM:
+            fz_write_bitmap_as_pbm(ctx, out, bitmap);
+            break;
+        case MUPDF_BITMAP_PKM:
+            fz_write_bitmap_as_pkm(ctx, out, bitmap);
+            break;
+        case MUPDF_BITMAP_PCL:
+        {
+            fz_pcl_options opts;
+            fz_pcl_preset(ctx, &opts, "generic");
+            fz_write_bitmap_as_pcl(ctx, out, bitmap, &opts);
+            break;
+        }
+        default:
+            fz_throw(ctx, FZ_ERROR_GENERIC, "unknown bitmap format");
+        }
+    }
+    fz_catch(ctx)
+    {
+        mupdf_save_error(ctx, errptr);
+    }
+}
@@ -0,0 +1,17 @@
+/// Formats a page can be streamed in by [`Page::write_bands`](crate::Page::write_bands)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum BandFormat {
+    /// Gray or RGB, with or without alpha
+    PNG = 0,
+    /// Gray or RGB without alpha
+    PNM = 1,
+    /// Any colorspace, with or without alpha
+    PAM = 2,
+    /// Color PCL, RGB without alpha
+    PCL = 3,
+    /// Gray or RGB without alpha
+    PCLm = 4,
+    /// Gray, RGB or CMYK without alpha
+    PWG = 5,
+}
@@ -1,10 +1,97 @@
 use std::convert::TryFrom;
+use std::fs::File;
+use std::io::Write;
 use std::slice;
 
 use mupdf_sys::*;
 
+use crate::output::Output;
 use crate::{context, Error, Pixmap};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum BitmapFormat {
+    /// Gray bitmaps only
+    PBM = 0,
+    /// CMYK bitmaps only
+    PKM = 1,
+    /// Monochrome PCL for gray bitmaps
+    PCL = 2,
+}
+
+/// A threshold matrix tiled over the pixmap when halftoning
+///
+/// A pixel is inked where its ink coverage (0 to 255) exceeds the threshold at its
+/// position. Gray pixmaps are inverted first, so black is full coverage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Halftone {
+    width: u32,
+    height: u32,
+    thresholds: Vec<u8>,
+}
+
+impl Halftone {
+    /// Custom threshold matrix of `width` x `height` values in row-major order
+    pub fn new(width: u32, height: u32, thresholds: Vec<u8>) -> Result<Self, Error> {
+        let cells = (width as usize).checked_mul(height as usize);
+        if width == 0 || height == 0 || cells != Some(thresholds.len()) {
+            return Err(Error::InvalidArgument(
+                "threshold matrix size does not match its dimensions".to_string(),
+            ));
+        }
+        Ok(Self {
+            width,
+            height,
+            thresholds,
+        })
+    }
+
+    /// The same threshold everywhere, i.e. no dithering
+    pub fn threshold(level: u8) -> Self {
+        Self {
+            width: 1,
+            height: 1,
+            thresholds: vec![level],
+        }
+    }
+
+    /// Ordered dither with a `2^order` x `2^order` Bayer matrix, `order` is clamped to 1..=4
+    pub fn bayer(order: u32) -> Self {
+        let order = order.clamp(1, 4);
+        let size = 1u32 << order;
+        let cells = size * size;
+        let mut thresholds = Vec::with_capacity(cells as usize);
+        for y in 0..size {
+            for x in 0..size {
+                // Interleave the bits of x ^ y and y to get the Bayer index
+                let (a, b) = (x ^ y, y);
+                let mut index = 0;
+                for bit in (0..order).rev() {
+                    index = (index << 2) | (((a >> bit) & 1) << 1) | ((b >> bit) & 1);
+                }
+                thresholds.push(((2 * index + 1) * 255 / (2 * cells)) as u8);
+            }
+        }
+        Self {
+            width: size,
+            height: size,
+            thresholds,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn thresholds(&self) -> &[u8] {
+        &self.thresholds
+    }
+}
+
 /// Bitmaps have 1 bit per component.
 /// Only used for creating halftoned versions of contone buffers, and saving out.
 /// Samples are stored msb first, akin to pbms.
@@ -19,6 +106,22 @@ impl Bitmap {
         Ok(Self { inner })
     }
 
+    /// Halftone a gray or CMYK pixmap with `halftone` instead of the default halftone
+    ///
+    /// Alpha is ignored.
+    pub fn from_pixmap_with_halftone(pixmap: &Pixmap, halftone: &Halftone) -> Result<Self, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_new_bitmap_from_pixmap_with_thresholds(
+                context(),
+                pixmap.inner,
+                halftone.thresholds.as_ptr(),
+                halftone.width as _,
+                halftone.height as _
+            ))
+        };
+        Ok(Self { inner })
+    }
+
     /// Width of the region in pixels.
     pub fn width(&self) -> u32 {
         unsafe { (*self.inner).w as u32 }
@@ -47,14 +150,31 @@ impl Bitmap {
     }
 
     pub fn samples(&self) -> &[u8] {
-        let len = (self.width() * self.height()) as usize;
+        let len = self.height() as usize * self.stride() as usize;
         unsafe { slice::from_raw_parts((*self.inner).samples, len) }
     }
 
     pub fn samples_mut(&mut self) -> &mut [u8] {
-        let len = (self.width() * self.height()) as usize;
+        let len = self.height() as usize * self.stride() as usize;
         unsafe { slice::from_raw_parts_mut((*self.inner).samples, len) }
     }
+
+    pub fn write_to<W: Write>(&self, w: &mut W, format: BitmapFormat) -> Result<(), Error> {
+        Output::write_with(w, |out| unsafe {
+            ffi_try!(mupdf_write_bitmap(
+                context(),
+                out,
+                self.inner,
+                format as i32
+            ));
+            Ok(())
+        })
+    }
+
+    pub fn save_as(&self, filename: &str, format: BitmapFormat) -> Result<(), Error> {
+        let mut file = File::create(filename)?;
+        self.write_to(&mut file, format)
+    }
 }
 
 impl Drop for Bitmap {
@@ -77,7 +197,8 @@ impl TryFrom<Pixmap> for Bitmap {
 
 #[cfg(test)]
 mod test {
-    use crate::{Bitmap, Colorspace, Pixmap};
+    use super::{BitmapFormat, Halftone};
+    use crate::{Bitmap, Colorspace, Error, Pixmap};
 
     #[test]
     fn test_new_bitmap() {
@@ -96,4 +217,35 @@ mod test {
         pixmap.clear().unwrap();
         assert!(Bitmap::from_pixmap(&pixmap).is_err());
     }
+
+    #[test]
+    fn test_bitmap_halftone() {
+        let bayer = Halftone::bayer(1);
+        assert_eq!(bayer.thresholds(), &[31, 159, 223, 95]);
+        assert!(Halftone::new(2, 2, vec![0; 3]).is_err());
+        // 65536 * 65536 wraps to 0 in u32
+        assert!(matches!(
+            Halftone::new(65536, 65536, Vec::new()),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let cs = Colorspace::device_gray();
+        let mut pixmap = Pixmap::new_with_w_h(&cs, 16, 2, false).unwrap();
+        pixmap.clear_with(0x80).unwrap();
+
+        let bitmap =
+            Bitmap::from_pixmap_with_halftone(&pixmap, &Halftone::threshold(0x80)).unwrap();
+        assert!(bitmap.samples().iter().all(|&b| b == 0));
+        let bitmap = Bitmap::from_pixmap_with_halftone(&pixmap, &bayer).unwrap();
+        // Half of the pixels are inked in a checkerboard
+        assert_eq!(&bitmap.samples()[..2], &[0xaa, 0xaa]);
+
+        let mut pbm = Vec::new();
+        bitmap.write_to(&mut pbm, BitmapFormat::PBM).unwrap();
+        assert!(pbm.starts_with(b"P4"));
+        let mut pcl = Vec::new();
+        bitmap.write_to(&mut pcl, BitmapFormat::PCL).unwrap();
+        assert!(!pcl.is_empty());
+        assert!(bitmap.write_to(&mut Vec::new(), BitmapFormat::PKM).is_err());
+    }
 }
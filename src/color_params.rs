@@ -64,13 +64,49 @@ impl From<ColorParams> for fz_color_params {
         let bp = ((flags >> 5) & 1) as u8;
         let op = ((flags >> 6) & 1) as u8;
         let opm = ((flags >> 7) & 1) as u8;
-        let ri = (flags & 32) as u8;
+        let ri = (flags & 3) as u8;
         fz_color_params { ri, bp, op, opm }
     }
 }
 
+impl From<fz_color_params> for ColorParams {
+    fn from(val: fz_color_params) -> Self {
+        let mut flags = (val.ri & 3) as i32;
+        if val.bp != 0 {
+            flags |= Self::BP;
+        }
+        if val.op != 0 {
+            flags |= Self::OP;
+        }
+        if val.opm != 0 {
+            flags |= Self::OPM;
+        }
+        Self(flags)
+    }
+}
+
 impl Default for ColorParams {
     fn default() -> Self {
         Self::new(RenderingIntent::RelativeColorimetric, true, false, false)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use mupdf_sys::fz_color_params;
+
+    use super::{ColorParams, RenderingIntent};
+
+    #[test]
+    fn test_color_params_rendering_intent() {
+        // The intent is stored in the two lowest bits, clear of the black point flag
+        let cp = ColorParams::new(RenderingIntent::AbsoluteColorimetric, true, false, true);
+        let fz: fz_color_params = cp.into();
+        assert_eq!((fz.ri, fz.bp, fz.op, fz.opm), (3, 1, 0, 1));
+        assert_eq!(ColorParams::from(fz), cp);
+
+        let cp = ColorParams::new(RenderingIntent::Saturation, false, true, false);
+        let fz: fz_color_params = cp.into();
+        assert_eq!((fz.ri, fz.bp, fz.op, fz.opm), (2, 0, 1, 0));
+    }
+}
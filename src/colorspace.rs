@@ -1,10 +1,34 @@
 use std::cmp::PartialEq;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::fmt;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::slice;
 
 use mupdf_sys::*;
 
-use crate::context;
+use crate::{context, ffi_error, ColorParams, Error};
+
+/// Tint transform of a Separation or DeviceN colorspace
+type TintFn = dyn Fn(&[f32], &mut [f32]) + Send + Sync;
+
+unsafe extern "C" fn eval_tint(
+    _ctx: *mut fz_context,
+    tint: *mut c_void,
+    s: *const f32,
+    sn: c_int,
+    d: *mut f32,
+    dn: c_int,
+) {
+    let tint = &*(tint as *const Box<TintFn>);
+    let src = slice::from_raw_parts(s, sn as usize);
+    let dst = slice::from_raw_parts_mut(d, dn as usize);
+    tint(src, dst);
+}
+
+unsafe extern "C" fn drop_tint(_ctx: *mut fz_context, tint: *mut c_void) {
+    drop(Box::from_raw(tint as *mut Box<TintFn>));
+}
 
 #[derive(Debug)]
 pub struct Colorspace {
@@ -17,25 +41,119 @@ impl Colorspace {
     }
 
     pub fn device_gray() -> Self {
-        let inner = unsafe { fz_device_gray(context()) };
+        let inner = unsafe { fz_keep_colorspace(context(), fz_device_gray(context())) };
         Self { inner }
     }
 
     pub fn device_rgb() -> Self {
-        let inner = unsafe { fz_device_rgb(context()) };
+        let inner = unsafe { fz_keep_colorspace(context(), fz_device_rgb(context())) };
         Self { inner }
     }
 
     pub fn device_bgr() -> Self {
-        let inner = unsafe { fz_device_bgr(context()) };
+        let inner = unsafe { fz_keep_colorspace(context(), fz_device_bgr(context())) };
         Self { inner }
     }
 
     pub fn device_cmyk() -> Self {
-        let inner = unsafe { fz_device_cmyk(context()) };
+        let inner = unsafe { fz_keep_colorspace(context(), fz_device_cmyk(context())) };
+        Self { inner }
+    }
+
+    /// CIE L*a*b* with a D50 white point
+    pub fn device_lab() -> Self {
+        let inner = unsafe { fz_keep_colorspace(context(), fz_device_lab(context())) };
         Self { inner }
     }
 
+    /// Load a colorspace from an ICC profile
+    pub fn from_icc(profile: &[u8]) -> Result<Self, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_new_icc_colorspace(
+                context(),
+                profile.as_ptr(),
+                profile.len()
+            ))
+        };
+        Ok(Self { inner })
+    }
+
+    /// Create an indexed colorspace with `high + 1` entries
+    ///
+    /// `lookup` holds the `base` color components of every entry, one byte per component.
+    pub fn new_indexed(base: &Colorspace, high: u8, lookup: &[u8]) -> Result<Self, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_new_indexed_colorspace(
+                context(),
+                base.inner,
+                high as _,
+                lookup.as_ptr(),
+                lookup.len()
+            ))
+        };
+        Ok(Self { inner })
+    }
+
+    /// Create a Separation colorspace for a single colorant
+    ///
+    /// `tint` maps the tint value (0.0 is no ink, 1.0 is full ink) to the components of `base`.
+    /// This relies on MuPDF internals and returns an error outside of MuPDF 1.16 to 1.25.
+    pub fn new_separation<F>(name: &str, base: &Colorspace, tint: F) -> Result<Self, Error>
+    where
+        F: Fn(f32, &mut [f32]) + Send + Sync + 'static,
+    {
+        Self::new_separation_imp(name, &[name], base, Box::new(move |s, d| tint(s[0], d)))
+    }
+
+    /// Create a DeviceN colorspace with one component per colorant
+    ///
+    /// `tint` maps the colorant values to the components of `base`. Needs the same MuPDF
+    /// versions as [`Colorspace::new_separation`].
+    pub fn new_device_n<F>(colorants: &[&str], base: &Colorspace, tint: F) -> Result<Self, Error>
+    where
+        F: Fn(&[f32], &mut [f32]) + Send + Sync + 'static,
+    {
+        Self::new_separation_imp("DeviceN", colorants, base, Box::new(tint))
+    }
+
+    fn new_separation_imp(
+        name: &str,
+        colorants: &[&str],
+        base: &Colorspace,
+        tint: Box<TintFn>,
+    ) -> Result<Self, Error> {
+        let c_name = CString::new(name)?;
+        let c_colorants = colorants
+            .iter()
+            .map(|colorant| CString::new(*colorant))
+            .collect::<Result<Vec<_>, _>>()?;
+        let c_colorant_ptrs: Vec<*const c_char> = c_colorants
+            .iter()
+            .map(|colorant| colorant.as_ptr())
+            .collect();
+        let tint = Box::into_raw(Box::new(tint));
+        unsafe {
+            let mut err = ptr::null_mut();
+            let inner = mupdf_new_separation_colorspace(
+                context(),
+                c_name.as_ptr(),
+                c_colorant_ptrs.len() as _,
+                c_colorant_ptrs.as_ptr() as *mut _,
+                base.inner,
+                Some(eval_tint),
+                Some(drop_tint),
+                tint as *mut c_void,
+                &mut err,
+            );
+            if !err.is_null() {
+                // The colorspace only takes ownership of the tint transform on success
+                drop(Box::from_raw(tint));
+                return Err(ffi_error(err).into());
+            }
+            Ok(Self { inner })
+        }
+    }
+
     pub fn n(&self) -> u32 {
         unsafe { fz_colorspace_n(context(), self.inner) as u32 }
     }
@@ -80,6 +198,36 @@ impl Colorspace {
         unsafe { fz_colorspace_is_subtractive(context(), self.inner) > 0 }
     }
 
+    /// Convert a single color from this colorspace to `dst`
+    ///
+    /// `color` needs at least [`Colorspace::n`] components, extra components are ignored.
+    pub fn convert_color(
+        &self,
+        dst: &Colorspace,
+        color: &[f32],
+        cp: ColorParams,
+    ) -> Result<Vec<f32>, Error> {
+        if color.len() < self.n() as usize {
+            return Err(Error::InvalidArgument(format!(
+                "{} color components given, colorspace has {}",
+                color.len(),
+                self.n()
+            )));
+        }
+        let mut out = vec![0.0; dst.n() as usize];
+        unsafe {
+            ffi_try!(mupdf_convert_color(
+                context(),
+                self.inner,
+                color.as_ptr(),
+                dst.inner,
+                out.as_mut_ptr(),
+                cp.into()
+            ));
+        }
+        Ok(out)
+    }
+
     pub fn name(&self) -> &str {
         let ptr = unsafe { fz_colorspace_name(context(), self.inner) };
         let name_cstr = unsafe { CStr::from_ptr(ptr) };
@@ -87,6 +235,21 @@ impl Colorspace {
     }
 }
 
+impl Clone for Colorspace {
+    fn clone(&self) -> Self {
+        let inner = unsafe { fz_keep_colorspace(context(), self.inner) };
+        Self { inner }
+    }
+}
+
+impl Drop for Colorspace {
+    fn drop(&mut self) {
+        if !self.inner.is_null() {
+            unsafe { fz_drop_colorspace(context(), self.inner) };
+        }
+    }
+}
+
 impl PartialEq for Colorspace {
     fn eq(&self, other: &Self) -> bool {
         self.inner == other.inner
@@ -104,9 +267,96 @@ impl fmt::Display for Colorspace {
     }
 }
 
+/// Colorspaces substituted for the device colorspaces while rendering
+///
+/// Content drawn in DeviceGray, DeviceRGB or DeviceCMYK is interpreted using these
+/// profiles, and the output intent (if any) is used to soft-proof the result.
+#[derive(Debug)]
+pub struct DefaultColorspaces {
+    pub(crate) inner: *mut fz_default_colorspaces,
+}
+
+impl DefaultColorspaces {
+    pub(crate) unsafe fn from_raw(inner: *mut fz_default_colorspaces) -> Self {
+        Self { inner }
+    }
+
+    /// The defaults of the current context, without an output intent
+    pub fn new() -> Result<Self, Error> {
+        let inner = unsafe { ffi_try!(mupdf_new_default_colorspaces(context())) };
+        Ok(Self { inner })
+    }
+
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let inner = unsafe { ffi_try!(mupdf_clone_default_colorspaces(context(), self.inner)) };
+        Ok(Self { inner })
+    }
+
+    pub fn gray(&self) -> Colorspace {
+        unsafe {
+            let cs = fz_default_gray(context(), self.inner);
+            Colorspace::from_raw(fz_keep_colorspace(context(), cs))
+        }
+    }
+
+    pub fn rgb(&self) -> Colorspace {
+        unsafe {
+            let cs = fz_default_rgb(context(), self.inner);
+            Colorspace::from_raw(fz_keep_colorspace(context(), cs))
+        }
+    }
+
+    pub fn cmyk(&self) -> Colorspace {
+        unsafe {
+            let cs = fz_default_cmyk(context(), self.inner);
+            Colorspace::from_raw(fz_keep_colorspace(context(), cs))
+        }
+    }
+
+    pub fn output_intent(&self) -> Option<Colorspace> {
+        unsafe {
+            let cs = fz_default_output_intent(context(), self.inner);
+            if cs.is_null() {
+                return None;
+            }
+            Some(Colorspace::from_raw(fz_keep_colorspace(context(), cs)))
+        }
+    }
+
+    /// Replace the default gray, RGB or CMYK colorspace, depending on the type of `cs`
+    pub fn set(&mut self, cs: &Colorspace) -> Result<&mut Self, Error> {
+        unsafe {
+            ffi_try!(mupdf_set_default_colorspace(
+                context(),
+                self.inner,
+                cs.inner
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Set the output intent, which also becomes the default for its colorspace type
+    /// unless that default was already replaced
+    pub fn set_output_intent(&mut self, cs: &Colorspace) -> &mut Self {
+        unsafe {
+            fz_set_default_output_intent(context(), self.inner, cs.inner);
+        }
+        self
+    }
+}
+
+impl Drop for DefaultColorspaces {
+    fn drop(&mut self) {
+        if !self.inner.is_null() {
+            unsafe { fz_drop_default_colorspaces(context(), self.inner) };
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Colorspace;
+    use super::{Colorspace, DefaultColorspaces};
+    use crate::ColorParams;
 
     #[test]
     fn test_color_space_device_colors() {
@@ -127,4 +377,83 @@ mod test {
         assert!(cmyk.is_cmyk());
         assert_eq!(cmyk.name(), "DeviceCMYK");
     }
+
+    #[test]
+    fn test_convert_color() {
+        let gray = Colorspace::device_gray();
+        let rgb = Colorspace::device_rgb();
+        let color = gray
+            .convert_color(&rgb, &[1.0], ColorParams::default())
+            .unwrap();
+        assert_eq!(color.len(), 3);
+        assert!(color.iter().all(|&c| (c - 1.0).abs() < 0.01));
+        assert!(rgb
+            .convert_color(&gray, &[1.0, 0.0], ColorParams::default())
+            .is_err());
+
+        let lab = Colorspace::device_lab();
+        assert!(lab.is_lab());
+        assert_eq!(lab.n(), 3);
+    }
+
+    #[test]
+    fn test_color_space_indexed() {
+        let rgb = Colorspace::device_rgb();
+        let indexed = Colorspace::new_indexed(&rgb, 1, &[255, 0, 0, 0, 0, 255]).unwrap();
+        assert!(indexed.is_indexed());
+        assert_eq!(indexed.n(), 1);
+        let color = indexed
+            .convert_color(&rgb, &[1.0], ColorParams::default())
+            .unwrap();
+        assert!(color[0] < 0.01 && color[1] < 0.01 && color[2] > 0.99);
+
+        assert!(Colorspace::new_indexed(&rgb, 1, &[255, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_color_space_separation() {
+        let rgb = Colorspace::device_rgb();
+        let spot = Colorspace::new_separation("Spot Red", &rgb, |tint, rgb| {
+            rgb[0] = 1.0;
+            rgb[1] = 1.0 - tint;
+            rgb[2] = 1.0 - tint;
+        })
+        .unwrap();
+        assert_eq!(spot.n(), 1);
+        let color = spot
+            .convert_color(&rgb, &[1.0], ColorParams::default())
+            .unwrap();
+        assert!(color[0] > 0.99 && color[1] < 0.01 && color[2] < 0.01);
+
+        let device_n = Colorspace::new_device_n(&["Red", "Blue"], &rgb, |s, rgb| {
+            rgb[0] = 1.0 - s[1];
+            rgb[1] = 1.0 - s[0] - s[1];
+            rgb[2] = 1.0 - s[0];
+        })
+        .unwrap();
+        assert_eq!(device_n.n(), 2);
+    }
+
+    #[test]
+    fn test_color_space_invalid_icc() {
+        assert!(Colorspace::from_icc(b"not an icc profile").is_err());
+    }
+
+    #[test]
+    fn test_default_colorspaces() {
+        let mut default_cs = DefaultColorspaces::new().unwrap();
+        assert!(default_cs.gray().is_gray());
+        assert!(default_cs.rgb().is_rgb());
+        assert!(default_cs.cmyk().is_cmyk());
+        assert!(default_cs.output_intent().is_none());
+
+        let lab = Colorspace::device_lab();
+        assert!(default_cs.set(&lab).is_err());
+
+        let cmyk = Colorspace::device_cmyk();
+        default_cs.set_output_intent(&cmyk);
+        assert_eq!(default_cs.output_intent().unwrap(), cmyk);
+        let cloned = default_cs.try_clone().unwrap();
+        assert!(cloned.output_intent().is_some());
+    }
 }
@@ -1,3 +1,7 @@
+use std::os::raw::c_int;
+use std::ptr;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+
 use mupdf_sys::*;
 
 use crate::{context, Error};
@@ -5,7 +9,7 @@ use crate::{context, Error};
 /// Provide two-way communication between application and library.
 /// Intended for multi-threaded applications where one thread is rendering pages and
 /// another thread wants to read progress feedback or abort a job that takes a long time to finish.
-/// The communication is unsynchronized without locking.
+/// Fields are accessed atomically without locking, so a `Cookie` can be shared between threads.
 #[derive(Debug)]
 pub struct Cookie {
     pub(crate) inner: *mut fz_cookie,
@@ -17,43 +21,51 @@ impl Cookie {
         Ok(Self { inner })
     }
 
-    /// Abort rendering
-    pub fn abort(&mut self) {
-        unsafe {
-            (*self.inner).abort = 1;
-        }
+    /// Abort rendering, may be called from another thread while rendering is in progress
+    pub fn abort(&self) {
+        unsafe { atomic(ptr::addr_of_mut!((*self.inner).abort)) }.store(1, Ordering::Relaxed);
+    }
+
+    /// Whether rendering has been aborted
+    pub fn aborted(&self) -> bool {
+        unsafe { atomic(ptr::addr_of_mut!((*self.inner).abort)) }.load(Ordering::Relaxed) != 0
     }
 
     /// Communicates rendering progress back to the application.
     /// Increments as a page is being rendered.
     pub fn progress(&self) -> i32 {
-        unsafe { (*self.inner).progress }
+        unsafe { atomic(ptr::addr_of_mut!((*self.inner).progress)) }.load(Ordering::Relaxed)
     }
 
     /// Communicates the known upper bound of rendering back to the application
     pub fn max_progress(&self) -> usize {
-        unsafe { (*self.inner).progress_max }
+        let max = unsafe { ptr::addr_of_mut!((*self.inner).progress_max) } as *const AtomicUsize;
+        unsafe { &*max }.load(Ordering::Relaxed)
     }
 
     /// count of errors during current rendering
     pub fn errors(&self) -> i32 {
-        unsafe { (*self.inner).errors }
+        unsafe { atomic(ptr::addr_of_mut!((*self.inner).errors)) }.load(Ordering::Relaxed)
     }
 
     /// Initially should be set to 0.
     /// Will be set to non-zero if a TRYLATER error is thrown during rendering
     pub fn incomplete(&self) -> bool {
-        unsafe { (*self.inner).incomplete > 0 }
+        unsafe { atomic(ptr::addr_of_mut!((*self.inner).incomplete)) }.load(Ordering::Relaxed) > 0
     }
 
     pub fn set_incomplete(&mut self, value: bool) {
         let val = if value { 1 } else { 0 };
-        unsafe {
-            (*self.inner).incomplete = val;
-        }
+        unsafe { atomic(ptr::addr_of_mut!((*self.inner).incomplete)) }
+            .store(val, Ordering::Relaxed);
     }
 }
 
+/// Access an `int` field of the cookie atomically, rendering threads update them concurrently
+unsafe fn atomic<'a>(field: *mut c_int) -> &'a AtomicI32 {
+    &*(field as *const AtomicI32)
+}
+
 impl Drop for Cookie {
     fn drop(&mut self) {
         if !self.inner.is_null() {
@@ -63,3 +75,7 @@ impl Drop for Cookie {
         }
     }
 }
+
+// A `Cookie` is meant to be polled and aborted from other threads while rendering
+unsafe impl Send for Cookie {}
+unsafe impl Sync for Cookie {}
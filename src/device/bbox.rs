@@ -0,0 +1,353 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::ptr;
+use std::rc::Rc;
+
+use mupdf_sys::*;
+
+use crate::{
+    context, BlendMode, ColorParams, Colorspace, Device, Error, Image, Matrix, NativeDevice, Path,
+    Rect, Shade, StrokeState, Text,
+};
+
+#[derive(Debug)]
+struct RawRect(*mut fz_rect);
+
+impl Drop for RawRect {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { drop(Box::from_raw(self.0)) };
+        }
+    }
+}
+
+/// A device that accumulates the bounding box of everything drawn to it
+#[derive(Debug)]
+pub struct BBoxDevice {
+    // `device` writes into `rect`, so it has to be dropped first
+    device: Device,
+    rect: RawRect,
+}
+
+impl BBoxDevice {
+    pub fn new() -> Result<Self, Error> {
+        let rect = RawRect(Box::into_raw(Box::new(fz_rect {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 0.0,
+            y1: 0.0,
+        })));
+        let dev = unsafe { ffi_try!(mupdf_new_bbox_device(context(), rect.0)) };
+        Ok(Self {
+            device: unsafe { Device::from_raw(dev, ptr::null_mut()) },
+            rect,
+        })
+    }
+
+    /// Bounding box of the content drawn so far, `None` if nothing visible was drawn
+    pub fn bounds(&self) -> Option<Rect> {
+        let rect: Rect = unsafe { *self.rect.0 }.into();
+        if rect.x0 >= rect.x1 || rect.y0 >= rect.y1 {
+            return None;
+        }
+        Some(rect)
+    }
+}
+
+impl Deref for BBoxDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Self::Target {
+        &self.device
+    }
+}
+
+/// Ink extent of a page, split by the kind of content
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ContentBounds {
+    pub all: Option<Rect>,
+    pub text: Option<Rect>,
+    pub images: Option<Rect>,
+    /// Filled and stroked paths as well as shadings
+    pub vectors: Option<Rect>,
+}
+
+fn union(a: Option<Rect>, b: Option<Rect>) -> Option<Rect> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(Rect::new(
+            a.x0.min(b.x0),
+            a.y0.min(b.y0),
+            a.x1.max(b.x1),
+            a.y1.max(b.y1),
+        )),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+struct SplitBounds {
+    text: BBoxDevice,
+    images: BBoxDevice,
+    vectors: BBoxDevice,
+    error: RefCell<Option<Error>>,
+}
+
+impl SplitBounds {
+    fn check(&self, res: Result<(), Error>) {
+        if let Err(err) = res {
+            self.error.borrow_mut().get_or_insert(err);
+        }
+    }
+
+    fn all<F: Fn(&Device) -> Result<(), Error>>(&self, f: F) {
+        self.check(f(&self.text));
+        self.check(f(&self.images));
+        self.check(f(&self.vectors));
+    }
+}
+
+/// Forwards drawing operations to the `SplitBounds` device matching their kind,
+/// clipping and grouping is forwarded to all of them.
+struct SplitBoundsDevice(Rc<SplitBounds>);
+
+fn null_colorspace() -> Colorspace {
+    unsafe { Colorspace::from_raw(ptr::null_mut()) }
+}
+
+impl NativeDevice for SplitBoundsDevice {
+    fn fill_path(
+        &mut self,
+        path: &Path,
+        even_odd: bool,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+        let b = &self.0;
+        b.check(
+            b.vectors
+                .fill_path(path, even_odd, ctm, cs, color, alpha, cp),
+        );
+    }
+
+    fn stroke_path(
+        &mut self,
+        path: &Path,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+        let b = &self.0;
+        b.check(
+            b.vectors
+                .stroke_path(path, stroke, ctm, cs, color, alpha, cp),
+        );
+    }
+
+    fn clip_path(&mut self, path: &Path, even_odd: bool, ctm: &Matrix, _scissor: Rect) {
+        self.0.all(|dev| dev.clip_path(path, even_odd, ctm));
+    }
+
+    fn clip_stroke_path(
+        &mut self,
+        path: &Path,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        _scissor: Rect,
+    ) {
+        self.0.all(|dev| dev.clip_stroke_path(path, stroke, ctm));
+    }
+
+    fn fill_text(
+        &mut self,
+        text: &Text,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+        let b = &self.0;
+        b.check(b.text.fill_text(text, ctm, cs, color, alpha, cp));
+    }
+
+    fn stroke_text(
+        &mut self,
+        text: &Text,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+        let b = &self.0;
+        b.check(b.text.stroke_text(text, stroke, ctm, cs, color, alpha, cp));
+    }
+
+    fn clip_text(&mut self, text: &Text, ctm: &Matrix, _scissor: Rect) {
+        self.0.all(|dev| dev.clip_text(text, ctm));
+    }
+
+    fn clip_stroke_text(
+        &mut self,
+        text: &Text,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        _scissor: Rect,
+    ) {
+        self.0.all(|dev| dev.clip_stroke_text(text, stroke, ctm));
+    }
+
+    fn fill_shade(&mut self, shade: &Shade, ctm: &Matrix, alpha: f32, cp: ColorParams) {
+        let b = &self.0;
+        b.check(b.vectors.fill_shade(shade, ctm, alpha, cp));
+    }
+
+    fn fill_image(&mut self, image: &Image, ctm: &Matrix, alpha: f32, cp: ColorParams) {
+        let b = &self.0;
+        b.check(b.images.fill_image(image, ctm, alpha, cp));
+    }
+
+    fn fill_image_mask(
+        &mut self,
+        image: &Image,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+        let b = &self.0;
+        b.check(b.images.fill_image_mask(image, ctm, cs, color, alpha, cp));
+    }
+
+    fn clip_image_mask(&mut self, image: &Image, ctm: &Matrix, _scissor: Rect) {
+        self.0.all(|dev| dev.clip_image_mask(image, ctm));
+    }
+
+    fn pop_clip(&mut self) {
+        self.0.all(|dev| dev.pop_clip());
+    }
+
+    fn begin_mask(
+        &mut self,
+        area: Rect,
+        luminosity: bool,
+        cs: Option<&Colorspace>,
+        bc: &[f32],
+        cp: ColorParams,
+    ) {
+        let null = null_colorspace();
+        let cs = cs.unwrap_or(&null);
+        self.0
+            .all(|dev| dev.begin_mask(area, luminosity, cs, bc, cp));
+    }
+
+    fn end_mask(&mut self) {
+        self.0.all(|dev| dev.end_mask());
+    }
+
+    fn begin_group(
+        &mut self,
+        area: Rect,
+        cs: Option<&Colorspace>,
+        isolated: bool,
+        knockout: bool,
+        blend_mode: BlendMode,
+        alpha: f32,
+    ) {
+        let null = null_colorspace();
+        let cs = cs.unwrap_or(&null);
+        self.0
+            .all(|dev| dev.begin_group(area, cs, isolated, knockout, blend_mode, alpha));
+    }
+
+    fn end_group(&mut self) {
+        self.0.all(|dev| dev.end_group());
+    }
+
+    fn begin_tile(
+        &mut self,
+        area: Rect,
+        view: Rect,
+        xstep: f32,
+        ystep: f32,
+        ctm: &Matrix,
+        id: i32,
+    ) -> bool {
+        self.0.all(|dev| {
+            dev.begin_tile(area, view, xstep, ystep, ctm, id)
+                .map(|_| ())
+        });
+        false
+    }
+
+    fn end_tile(&mut self) {
+        self.0.all(|dev| dev.end_tile());
+    }
+}
+
+/// Run `f` against a device that records the ink extent per kind of content
+pub(crate) fn content_bounds<F>(f: F) -> Result<ContentBounds, Error>
+where
+    F: FnOnce(&Device) -> Result<(), Error>,
+{
+    let bounds = Rc::new(SplitBounds {
+        text: BBoxDevice::new()?,
+        images: BBoxDevice::new()?,
+        vectors: BBoxDevice::new()?,
+        error: RefCell::new(None),
+    });
+    let device = Device::from_native(SplitBoundsDevice(bounds.clone()))?;
+    f(&device)?;
+    drop(device);
+
+    if let Some(err) = bounds.error.borrow_mut().take() {
+        return Err(err);
+    }
+    let text = bounds.text.bounds();
+    let images = bounds.images.bounds();
+    let vectors = bounds.vectors.bounds();
+    Ok(ContentBounds {
+        all: union(union(text, images), vectors),
+        text,
+        images,
+        vectors,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::BBoxDevice;
+    use crate::{ColorParams, Colorspace, Matrix, Path, Rect};
+
+    #[test]
+    fn test_bbox_device() {
+        let device = BBoxDevice::new().unwrap();
+        assert_eq!(device.bounds(), None);
+
+        let mut path = Path::new().unwrap();
+        path.move_to(10.0, 20.0).unwrap();
+        path.line_to(30.0, 20.0).unwrap();
+        path.line_to(30.0, 40.0).unwrap();
+        path.close().unwrap();
+        device
+            .fill_path(
+                &path,
+                false,
+                &Matrix::IDENTITY,
+                &Colorspace::device_rgb(),
+                &[1.0, 0.0, 0.0],
+                1.0,
+                ColorParams::default(),
+            )
+            .unwrap();
+        assert_eq!(device.bounds(), Some(Rect::new(10.0, 20.0, 30.0, 40.0)));
+    }
+}
@@ -0,0 +1,67 @@
+use std::ptr;
+
+use bitflags::bitflags;
+use mupdf_sys::*;
+
+use crate::{context, Device, Error};
+
+bitflags! {
+    /// Kinds of content a filtered device drops instead of forwarding
+    pub struct DeviceFilter: u32 {
+        const TEXT = 1;
+        /// Images drawn with their own colors
+        const IMAGES = 2;
+        /// Filled and stroked paths as well as shadings
+        const VECTORS = 4;
+        /// Stencil masks filled with a single color
+        const IMAGE_MASKS = 8;
+    }
+}
+
+impl Device {
+    /// Create a device that forwards everything to `device` except the content kinds in `skip`
+    ///
+    /// Clipping, masks, groups, tiles and layers are always forwarded, so the remaining
+    /// content is drawn exactly as it would have been without the filter.
+    pub fn filtered(device: &Device, skip: DeviceFilter) -> Result<Self, Error> {
+        let dev = unsafe {
+            ffi_try!(mupdf_new_filter_device(
+                context(),
+                device.dev,
+                skip.bits() as _
+            ))
+        };
+        Ok(unsafe { Device::from_raw(dev, ptr::null_mut()) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DeviceFilter;
+    use crate::{Colorspace, Device, Document, Matrix, Pixmap};
+
+    #[test]
+    fn test_filtered_device() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let bounds = page0.bounds().unwrap();
+        let (w, h) = (bounds.width() as i32, bounds.height() as i32);
+
+        let mut pixmap = Pixmap::new_with_w_h(&Colorspace::device_gray(), w, h, false).unwrap();
+        pixmap.clear_with(0xff).unwrap();
+        {
+            let draw = Device::from_pixmap(&pixmap).unwrap();
+            let device = Device::filtered(&draw, DeviceFilter::TEXT).unwrap();
+            page0.run(&device, &Matrix::IDENTITY).unwrap();
+        }
+        assert!(pixmap.samples().iter().all(|&v| v == 0xff));
+
+        pixmap.clear_with(0xff).unwrap();
+        {
+            let draw = Device::from_pixmap(&pixmap).unwrap();
+            let device = Device::filtered(&draw, DeviceFilter::IMAGES).unwrap();
+            page0.run(&device, &Matrix::IDENTITY).unwrap();
+        }
+        assert!(pixmap.samples().iter().any(|&v| v != 0xff));
+    }
+}
@@ -2,14 +2,29 @@ use std::ffi::CString;
 use std::ptr;
 
 use mupdf_sys::*;
+use num_enum::TryFromPrimitive;
 
 use crate::{
     context, ColorParams, Colorspace, DisplayList, Error, IRect, Image, Matrix, Path, Pixmap, Rect,
     Shade, StrokeState, Text, TextPage, TextPageOptions,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-#[repr(C)]
+mod bbox;
+mod filter;
+mod native;
+mod test_device;
+mod trace;
+
+pub(crate) use bbox::content_bounds;
+pub use bbox::{BBoxDevice, ContentBounds};
+pub use filter::DeviceFilter;
+pub use native::NativeDevice;
+pub(crate) use test_device::is_color;
+pub use test_device::{ColorUsage, TestDevice, TestDeviceOptions};
+pub use trace::{DeviceEvent, TraceColor, TraceDevice, TraceGlyph, TraceSpan};
+
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
+#[repr(u32)]
 pub enum BlendMode {
     /* PDF 1.4 -- standard separable */
     Normal = 0,
@@ -438,7 +453,12 @@ impl Drop for Device {
     fn drop(&mut self) {
         if !self.dev.is_null() {
             unsafe {
-                fz_close_device(context(), self.dev);
+                // Closing can throw, e.g. from a native device, but dropping can't report it
+                let mut err = ptr::null_mut();
+                mupdf_close_device(context(), self.dev, &mut err);
+                if !err.is_null() {
+                    mupdf_drop_error(err);
+                }
                 fz_drop_device(context(), self.dev);
             }
         }
@@ -0,0 +1,686 @@
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::mem::{self, ManuallyDrop};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use mupdf_sys::*;
+
+use crate::{
+    context, BlendMode, ColorParams, Colorspace, Device, Error, Image, Matrix, Path, Rect, Shade,
+    StrokeState, Text,
+};
+
+/// Callbacks for a device implemented in Rust.
+///
+/// Every method has an empty default implementation, so implementors only need to
+/// override the operations they are interested in.
+/// A native device is turned into a [`Device`] with [`Device::from_native`] and can then be
+/// passed to `Page::run` or `DisplayList::run` like any other device.
+#[allow(unused_variables)]
+pub trait NativeDevice {
+    fn close_device(&mut self) {}
+
+    fn fill_path(
+        &mut self,
+        path: &Path,
+        even_odd: bool,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+    }
+
+    fn stroke_path(
+        &mut self,
+        path: &Path,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+    }
+
+    fn clip_path(&mut self, path: &Path, even_odd: bool, ctm: &Matrix, scissor: Rect) {}
+
+    fn clip_stroke_path(&mut self, path: &Path, stroke: &StrokeState, ctm: &Matrix, scissor: Rect) {
+    }
+
+    fn fill_text(
+        &mut self,
+        text: &Text,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+    }
+
+    fn stroke_text(
+        &mut self,
+        text: &Text,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+    }
+
+    fn clip_text(&mut self, text: &Text, ctm: &Matrix, scissor: Rect) {}
+
+    fn clip_stroke_text(&mut self, text: &Text, stroke: &StrokeState, ctm: &Matrix, scissor: Rect) {
+    }
+
+    fn ignore_text(&mut self, text: &Text, ctm: &Matrix) {}
+
+    fn fill_shade(&mut self, shade: &Shade, ctm: &Matrix, alpha: f32, cp: ColorParams) {}
+
+    fn fill_image(&mut self, image: &Image, ctm: &Matrix, alpha: f32, cp: ColorParams) {}
+
+    fn fill_image_mask(
+        &mut self,
+        image: &Image,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+    }
+
+    fn clip_image_mask(&mut self, image: &Image, ctm: &Matrix, scissor: Rect) {}
+
+    fn pop_clip(&mut self) {}
+
+    fn begin_mask(
+        &mut self,
+        area: Rect,
+        luminosity: bool,
+        cs: Option<&Colorspace>,
+        bc: &[f32],
+        cp: ColorParams,
+    ) {
+    }
+
+    fn end_mask(&mut self) {}
+
+    fn begin_group(
+        &mut self,
+        area: Rect,
+        cs: Option<&Colorspace>,
+        isolated: bool,
+        knockout: bool,
+        blend_mode: BlendMode,
+        alpha: f32,
+    ) {
+    }
+
+    fn end_group(&mut self) {}
+
+    /// Returns `true` if the tile content is already cached and does not need to be drawn again
+    fn begin_tile(
+        &mut self,
+        area: Rect,
+        view: Rect,
+        xstep: f32,
+        ystep: f32,
+        ctm: &Matrix,
+        id: i32,
+    ) -> bool {
+        false
+    }
+
+    fn end_tile(&mut self) {}
+
+    fn begin_layer(&mut self, name: &str) {}
+
+    fn end_layer(&mut self) {}
+}
+
+/// Memory layout of a native device, `fz_device` must be the first field
+/// so MuPDF can treat a pointer to it as a plain `fz_device`.
+#[repr(C)]
+struct CDevice<D> {
+    base: fz_device,
+    native: D,
+}
+
+impl<D> CDevice<D> {
+    /// The device is allocated by MuPDF, which only guarantees the alignment of `fz_device`
+    const ALIGNED: () = assert!(
+        mem::align_of::<D>() <= mem::align_of::<fz_device>(),
+        "native device is over-aligned"
+    );
+}
+
+impl Device {
+    /// Create a device whose operations are forwarded to `device`.
+    ///
+    /// The native device is owned by the returned `Device` and dropped with it,
+    /// use shared ownership (e.g. `Rc<RefCell<_>>`) to read back collected data.
+    /// Device types aligned beyond `fz_device` (8 bytes on 64-bit targets) are rejected at
+    /// compile time, box such fields instead.
+    pub fn from_native<D: NativeDevice + 'static>(device: D) -> Result<Self, Error> {
+        #[allow(clippy::let_unit_value)]
+        let () = CDevice::<D>::ALIGNED;
+        let size = mem::size_of::<CDevice<D>>() as c_int;
+        let dev = unsafe { ffi_try!(mupdf_new_derived_device(context(), size)) };
+        unsafe {
+            let cdev = dev as *mut CDevice<D>;
+            ptr::write(ptr::addr_of_mut!((*cdev).native), device);
+
+            (*dev).close_device = Some(close_device::<D>);
+            (*dev).drop_device = Some(drop_device::<D>);
+            (*dev).fill_path = Some(fill_path::<D>);
+            (*dev).stroke_path = Some(stroke_path::<D>);
+            (*dev).clip_path = Some(clip_path::<D>);
+            (*dev).clip_stroke_path = Some(clip_stroke_path::<D>);
+            (*dev).fill_text = Some(fill_text::<D>);
+            (*dev).stroke_text = Some(stroke_text::<D>);
+            (*dev).clip_text = Some(clip_text::<D>);
+            (*dev).clip_stroke_text = Some(clip_stroke_text::<D>);
+            (*dev).ignore_text = Some(ignore_text::<D>);
+            (*dev).fill_shade = Some(fill_shade::<D>);
+            (*dev).fill_image = Some(fill_image::<D>);
+            (*dev).fill_image_mask = Some(fill_image_mask::<D>);
+            (*dev).clip_image_mask = Some(clip_image_mask::<D>);
+            (*dev).pop_clip = Some(pop_clip::<D>);
+            (*dev).begin_mask = Some(begin_mask::<D>);
+            (*dev).end_mask = Some(end_mask::<D>);
+            (*dev).begin_group = Some(begin_group::<D>);
+            (*dev).end_group = Some(end_group::<D>);
+            (*dev).begin_tile = Some(begin_tile::<D>);
+            (*dev).end_tile = Some(end_tile::<D>);
+            (*dev).begin_layer = Some(begin_layer::<D>);
+            (*dev).end_layer = Some(end_layer::<D>);
+
+            Ok(Device::from_raw(dev, ptr::null_mut()))
+        }
+    }
+}
+
+unsafe fn native<'a, D>(dev: *mut fz_device) -> &'a mut D {
+    &mut (*(dev as *mut CDevice<D>)).native
+}
+
+// The following helpers wrap borrowed MuPDF objects without taking a reference,
+// `ManuallyDrop` makes sure they are not released when the callback returns.
+unsafe fn borrow_path(path: *const fz_path) -> ManuallyDrop<Path> {
+    ManuallyDrop::new(Path::from_raw(path as *mut _))
+}
+
+unsafe fn borrow_text(text: *const fz_text) -> ManuallyDrop<Text> {
+    ManuallyDrop::new(Text::from_raw(text as *mut _))
+}
+
+unsafe fn borrow_stroke(stroke: *const fz_stroke_state) -> ManuallyDrop<StrokeState> {
+    ManuallyDrop::new(StrokeState::from_raw(stroke as *mut _))
+}
+
+unsafe fn borrow_image(image: *mut fz_image) -> ManuallyDrop<Image> {
+    ManuallyDrop::new(Image::from_raw(image))
+}
+
+unsafe fn borrow_colorspace(cs: *mut fz_colorspace) -> Option<ManuallyDrop<Colorspace>> {
+    if cs.is_null() {
+        return None;
+    }
+    Some(ManuallyDrop::new(Colorspace::from_raw(cs)))
+}
+
+unsafe fn color_slice<'a>(
+    ctx: *mut fz_context,
+    cs: *mut fz_colorspace,
+    color: *const f32,
+) -> &'a [f32] {
+    if cs.is_null() || color.is_null() {
+        return &[];
+    }
+    let n = fz_colorspace_n(ctx, cs) as usize;
+    slice::from_raw_parts(color, n)
+}
+
+/// Run the body of a callback, turning a panic into a MuPDF exception.
+///
+/// The panic is fully unwound and its payload dropped before `fz_throw` jumps out,
+/// so no Rust frame with pending destructors is skipped.
+unsafe fn guard<R>(ctx: *mut fz_context, f: impl FnOnce() -> R) -> R {
+    if let Ok(value) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        return value;
+    }
+    mupdf_throw(ctx, b"panic in native device\0".as_ptr() as *const c_char);
+    unreachable!()
+}
+
+unsafe extern "C" fn close_device<D: NativeDevice>(ctx: *mut fz_context, dev: *mut fz_device) {
+    guard(ctx, || {
+        native::<D>(dev).close_device();
+    })
+}
+
+unsafe extern "C" fn drop_device<D: NativeDevice>(_ctx: *mut fz_context, dev: *mut fz_device) {
+    // Dropping must not throw, a panicking destructor only leaks what is left
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        ptr::drop_in_place(native::<D>(dev) as *mut D);
+    }));
+}
+
+unsafe extern "C" fn fill_path<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    path: *const fz_path,
+    even_odd: c_int,
+    ctm: fz_matrix,
+    cs: *mut fz_colorspace,
+    color: *const f32,
+    alpha: f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let path = borrow_path(path);
+        let cs = ManuallyDrop::new(Colorspace::from_raw(cs));
+        let color = color_slice(ctx, cs.inner, color);
+        native::<D>(dev).fill_path(
+            &path,
+            even_odd != 0,
+            &ctm.into(),
+            &cs,
+            color,
+            alpha,
+            cp.into(),
+        );
+    })
+}
+
+unsafe extern "C" fn stroke_path<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    path: *const fz_path,
+    stroke: *const fz_stroke_state,
+    ctm: fz_matrix,
+    cs: *mut fz_colorspace,
+    color: *const f32,
+    alpha: f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let path = borrow_path(path);
+        let stroke = borrow_stroke(stroke);
+        let cs = ManuallyDrop::new(Colorspace::from_raw(cs));
+        let color = color_slice(ctx, cs.inner, color);
+        native::<D>(dev).stroke_path(&path, &stroke, &ctm.into(), &cs, color, alpha, cp.into());
+    })
+}
+
+unsafe extern "C" fn clip_path<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    path: *const fz_path,
+    even_odd: c_int,
+    ctm: fz_matrix,
+    scissor: fz_rect,
+) {
+    guard(ctx, || {
+        let path = borrow_path(path);
+        native::<D>(dev).clip_path(&path, even_odd != 0, &ctm.into(), scissor.into());
+    })
+}
+
+unsafe extern "C" fn clip_stroke_path<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    path: *const fz_path,
+    stroke: *const fz_stroke_state,
+    ctm: fz_matrix,
+    scissor: fz_rect,
+) {
+    guard(ctx, || {
+        let path = borrow_path(path);
+        let stroke = borrow_stroke(stroke);
+        native::<D>(dev).clip_stroke_path(&path, &stroke, &ctm.into(), scissor.into());
+    })
+}
+
+unsafe extern "C" fn fill_text<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    text: *const fz_text,
+    ctm: fz_matrix,
+    cs: *mut fz_colorspace,
+    color: *const f32,
+    alpha: f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let text = borrow_text(text);
+        let cs = ManuallyDrop::new(Colorspace::from_raw(cs));
+        let color = color_slice(ctx, cs.inner, color);
+        native::<D>(dev).fill_text(&text, &ctm.into(), &cs, color, alpha, cp.into());
+    })
+}
+
+unsafe extern "C" fn stroke_text<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    text: *const fz_text,
+    stroke: *const fz_stroke_state,
+    ctm: fz_matrix,
+    cs: *mut fz_colorspace,
+    color: *const f32,
+    alpha: f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let text = borrow_text(text);
+        let stroke = borrow_stroke(stroke);
+        let cs = ManuallyDrop::new(Colorspace::from_raw(cs));
+        let color = color_slice(ctx, cs.inner, color);
+        native::<D>(dev).stroke_text(&text, &stroke, &ctm.into(), &cs, color, alpha, cp.into());
+    })
+}
+
+unsafe extern "C" fn clip_text<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    text: *const fz_text,
+    ctm: fz_matrix,
+    scissor: fz_rect,
+) {
+    guard(ctx, || {
+        let text = borrow_text(text);
+        native::<D>(dev).clip_text(&text, &ctm.into(), scissor.into());
+    })
+}
+
+unsafe extern "C" fn clip_stroke_text<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    text: *const fz_text,
+    stroke: *const fz_stroke_state,
+    ctm: fz_matrix,
+    scissor: fz_rect,
+) {
+    guard(ctx, || {
+        let text = borrow_text(text);
+        let stroke = borrow_stroke(stroke);
+        native::<D>(dev).clip_stroke_text(&text, &stroke, &ctm.into(), scissor.into());
+    })
+}
+
+unsafe extern "C" fn ignore_text<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    text: *const fz_text,
+    ctm: fz_matrix,
+) {
+    guard(ctx, || {
+        let text = borrow_text(text);
+        native::<D>(dev).ignore_text(&text, &ctm.into());
+    })
+}
+
+unsafe extern "C" fn fill_shade<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    shade: *mut fz_shade,
+    ctm: fz_matrix,
+    alpha: f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let shade = ManuallyDrop::new(Shade { inner: shade });
+        native::<D>(dev).fill_shade(&shade, &ctm.into(), alpha, cp.into());
+    })
+}
+
+unsafe extern "C" fn fill_image<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    image: *mut fz_image,
+    ctm: fz_matrix,
+    alpha: f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let image = borrow_image(image);
+        native::<D>(dev).fill_image(&image, &ctm.into(), alpha, cp.into());
+    })
+}
+
+unsafe extern "C" fn fill_image_mask<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    image: *mut fz_image,
+    ctm: fz_matrix,
+    cs: *mut fz_colorspace,
+    color: *const f32,
+    alpha: f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let image = borrow_image(image);
+        let cs = ManuallyDrop::new(Colorspace::from_raw(cs));
+        let color = color_slice(ctx, cs.inner, color);
+        native::<D>(dev).fill_image_mask(&image, &ctm.into(), &cs, color, alpha, cp.into());
+    })
+}
+
+unsafe extern "C" fn clip_image_mask<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    image: *mut fz_image,
+    ctm: fz_matrix,
+    scissor: fz_rect,
+) {
+    guard(ctx, || {
+        let image = borrow_image(image);
+        native::<D>(dev).clip_image_mask(&image, &ctm.into(), scissor.into());
+    })
+}
+
+unsafe extern "C" fn pop_clip<D: NativeDevice>(ctx: *mut fz_context, dev: *mut fz_device) {
+    guard(ctx, || {
+        native::<D>(dev).pop_clip();
+    })
+}
+
+unsafe extern "C" fn begin_mask<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    area: fz_rect,
+    luminosity: c_int,
+    cs: *mut fz_colorspace,
+    bc: *const f32,
+    cp: fz_color_params,
+) {
+    guard(ctx, || {
+        let bc = color_slice(ctx, cs, bc);
+        let cs = borrow_colorspace(cs);
+        native::<D>(dev).begin_mask(area.into(), luminosity != 0, cs.as_deref(), bc, cp.into());
+    })
+}
+
+unsafe extern "C" fn end_mask<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    _tr: *mut fz_function,
+) {
+    guard(ctx, || {
+        native::<D>(dev).end_mask();
+    })
+}
+
+unsafe extern "C" fn begin_group<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    area: fz_rect,
+    cs: *mut fz_colorspace,
+    isolated: c_int,
+    knockout: c_int,
+    blendmode: c_int,
+    alpha: f32,
+) {
+    guard(ctx, || {
+        let cs = borrow_colorspace(cs);
+        let blend_mode = BlendMode::try_from(blendmode as u32).unwrap_or(BlendMode::Normal);
+        native::<D>(dev).begin_group(
+            area.into(),
+            cs.as_deref(),
+            isolated != 0,
+            knockout != 0,
+            blend_mode,
+            alpha,
+        );
+    })
+}
+
+unsafe extern "C" fn end_group<D: NativeDevice>(ctx: *mut fz_context, dev: *mut fz_device) {
+    guard(ctx, || {
+        native::<D>(dev).end_group();
+    })
+}
+
+unsafe extern "C" fn begin_tile<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    area: fz_rect,
+    view: fz_rect,
+    xstep: f32,
+    ystep: f32,
+    ctm: fz_matrix,
+    id: c_int,
+    _doc_id: c_int,
+) -> c_int {
+    guard(ctx, || {
+        native::<D>(dev).begin_tile(area.into(), view.into(), xstep, ystep, &ctm.into(), id)
+            as c_int
+    })
+}
+
+unsafe extern "C" fn end_tile<D: NativeDevice>(ctx: *mut fz_context, dev: *mut fz_device) {
+    guard(ctx, || {
+        native::<D>(dev).end_tile();
+    })
+}
+
+unsafe extern "C" fn begin_layer<D: NativeDevice>(
+    ctx: *mut fz_context,
+    dev: *mut fz_device,
+    name: *const c_char,
+) {
+    guard(ctx, || {
+        let name = if name.is_null() {
+            ""
+        } else {
+            CStr::from_ptr(name).to_str().unwrap_or("")
+        };
+        native::<D>(dev).begin_layer(name);
+    })
+}
+
+unsafe extern "C" fn end_layer<D: NativeDevice>(ctx: *mut fz_context, dev: *mut fz_device) {
+    guard(ctx, || {
+        native::<D>(dev).end_layer();
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::NativeDevice;
+    use crate::{ColorParams, Colorspace, Device, Document, Matrix, Text};
+
+    #[derive(Default)]
+    struct Counter {
+        fill_text: usize,
+        closed: bool,
+    }
+
+    struct TextCounter(Rc<RefCell<Counter>>);
+
+    impl NativeDevice for TextCounter {
+        fn close_device(&mut self) {
+            self.0.borrow_mut().closed = true;
+        }
+
+        fn fill_text(
+            &mut self,
+            _text: &Text,
+            _ctm: &Matrix,
+            _cs: &Colorspace,
+            _color: &[f32],
+            _alpha: f32,
+            _cp: ColorParams,
+        ) {
+            self.0.borrow_mut().fill_text += 1;
+        }
+    }
+
+    #[test]
+    fn test_native_device() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+
+        let counter = Rc::new(RefCell::new(Counter::default()));
+        let device = Device::from_native(TextCounter(counter.clone())).unwrap();
+        page0.run(&device, &Matrix::IDENTITY).unwrap();
+        assert!(counter.borrow().fill_text > 0);
+
+        drop(device);
+        assert!(counter.borrow().closed);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    struct Panicking;
+
+    impl NativeDevice for Panicking {
+        fn fill_text(
+            &mut self,
+            _text: &Text,
+            _ctm: &Matrix,
+            _cs: &Colorspace,
+            _color: &[f32],
+            _alpha: f32,
+            _cp: ColorParams,
+        ) {
+            panic!("fill_text");
+        }
+    }
+
+    #[test]
+    fn test_native_device_panic() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+
+        let device = Device::from_native(Panicking).unwrap();
+        assert!(page0.run(&device, &Matrix::IDENTITY).is_err());
+    }
+
+    struct PanickingClose(Rc<RefCell<Counter>>);
+
+    impl NativeDevice for PanickingClose {
+        fn close_device(&mut self) {
+            panic!("close_device");
+        }
+    }
+
+    #[test]
+    fn test_native_device_close_panic() {
+        let counter = Rc::new(RefCell::new(Counter::default()));
+        let device = Device::from_native(PanickingClose(counter.clone())).unwrap();
+        // The panic is caught and the error discarded, the native device is still dropped
+        drop(device);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
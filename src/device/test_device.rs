@@ -0,0 +1,172 @@
+use std::ops::Deref;
+use std::os::raw::c_int;
+use std::ptr;
+
+use bitflags::bitflags;
+use mupdf_sys::*;
+
+use crate::{context, Device, DeviceFilter, Error};
+
+bitflags! {
+    /// Options for the color test device
+    pub struct TestDeviceOptions: u32 {
+        /// Test every pixel of images instead of assuming they may be colored
+        const IMAGES = FZ_TEST_OPT_IMAGES as _;
+        /// Test every pixel of shadings instead of assuming they may be colored
+        const SHADINGS = FZ_TEST_OPT_SHADINGS as _;
+    }
+}
+
+/// Result of running content through a [`TestDevice`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorUsage {
+    /// Only neutral colors were used
+    Grayscale,
+    /// An image or shading was not tested and may contain color
+    MaybeColor,
+    /// At least one color exceeded the threshold
+    Color,
+}
+
+#[derive(Debug)]
+struct RawInt(*mut c_int);
+
+impl Drop for RawInt {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { drop(Box::from_raw(self.0)) };
+        }
+    }
+}
+
+/// A device that detects whether the content drawn to it uses color
+#[derive(Debug)]
+pub struct TestDevice {
+    // `device` writes into `is_color`, so it has to be dropped first
+    device: Device,
+    is_color: RawInt,
+}
+
+impl TestDevice {
+    /// Create a new test device
+    ///
+    /// ## Params
+    ///
+    /// * `threshold` - how far (from 0.0 to 1.0) a color may deviate from gray before it is considered colored
+    pub fn new(threshold: f32, options: TestDeviceOptions) -> Result<Self, Error> {
+        let is_color = RawInt(Box::into_raw(Box::new(0)));
+        let dev = unsafe {
+            ffi_try!(mupdf_new_test_device(
+                context(),
+                is_color.0,
+                threshold,
+                options.bits() as _
+            ))
+        };
+        Ok(Self {
+            device: unsafe { Device::from_raw(dev, ptr::null_mut()) },
+            is_color,
+        })
+    }
+
+    pub fn color_usage(&self) -> ColorUsage {
+        match unsafe { *self.is_color.0 } {
+            0 => ColorUsage::Grayscale,
+            1 => ColorUsage::MaybeColor,
+            _ => ColorUsage::Color,
+        }
+    }
+}
+
+impl Deref for TestDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Self::Target {
+        &self.device
+    }
+}
+
+/// Run `f` against a test device and report whether any color was used
+pub(crate) fn is_color<F>(threshold: f32, ignore_images: bool, f: F) -> Result<bool, Error>
+where
+    F: FnOnce(&Device) -> Result<(), Error>,
+{
+    let mut options = TestDeviceOptions::SHADINGS;
+    if !ignore_images {
+        options |= TestDeviceOptions::IMAGES;
+    }
+    let test = TestDevice::new(threshold, options)?;
+    if ignore_images {
+        let device = Device::filtered(&test, DeviceFilter::IMAGES)?;
+        f(&device)?;
+    } else {
+        f(&test)?;
+    }
+    Ok(test.color_usage() != ColorUsage::Grayscale)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_color, ColorUsage, TestDevice, TestDeviceOptions};
+    use crate::{ColorParams, Colorspace, Device, Image, Matrix, Path, Pixmap};
+
+    fn fill_rect(device: &TestDevice, cs: &Colorspace, color: &[f32]) {
+        let mut path = Path::new().unwrap();
+        path.rect(0, 0, 10, 10).unwrap();
+        device
+            .fill_path(
+                &path,
+                false,
+                &Matrix::IDENTITY,
+                cs,
+                color,
+                1.0,
+                ColorParams::default(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_test_device() {
+        let rgb = Colorspace::device_rgb();
+
+        let device = TestDevice::new(0.02, TestDeviceOptions::all()).unwrap();
+        fill_rect(&device, &rgb, &[0.5, 0.5, 0.5]);
+        assert_eq!(device.color_usage(), ColorUsage::Grayscale);
+
+        fill_rect(&device, &rgb, &[1.0, 0.0, 0.0]);
+        assert_eq!(device.color_usage(), ColorUsage::Color);
+    }
+
+    #[test]
+    fn test_is_color_ignore_images() {
+        let gray = Colorspace::device_gray();
+        let rgb = Colorspace::device_rgb();
+        let mut pixmap = Pixmap::new_with_w_h(&rgb, 4, 4, false).unwrap();
+        pixmap.clear_with(0x80).unwrap();
+        pixmap.samples_mut()[0] = 0xff;
+        let image = Image::from_pixmap(&pixmap).unwrap();
+        let mut mask = Pixmap::new_with_w_h(&gray, 4, 4, false).unwrap();
+        mask.clear_with(0xff).unwrap();
+        let mask = Image::from_pixmap(&mask).unwrap();
+
+        let draw_image = |device: &Device| {
+            device.fill_image(&image, &Matrix::IDENTITY, 1.0, ColorParams::default())
+        };
+        assert!(is_color(0.02, false, draw_image).unwrap());
+        assert!(!is_color(0.02, true, draw_image).unwrap());
+
+        // A stencil mask is painted in its fill color, which is not ignored
+        let draw_mask = |device: &Device| {
+            device.fill_image_mask(
+                &mask,
+                &Matrix::IDENTITY,
+                &rgb,
+                &[1.0, 0.0, 0.0],
+                1.0,
+                ColorParams::default(),
+            )
+        };
+        assert!(is_color(0.02, true, draw_mask).unwrap());
+    }
+}
@@ -0,0 +1,509 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::drawing::path_segments;
+use crate::{
+    BlendMode, ColorParams, Colorspace, Device, DrawingSegment, DrawingStroke, Error, Image,
+    Matrix, NativeDevice, Path, Point, Rect, Shade, StrokeState, Text, WriteMode,
+};
+
+/// Color of a drawing operation as it was passed to the device
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceColor {
+    pub colorspace: String,
+    pub components: Vec<f32>,
+    pub alpha: f32,
+}
+
+impl TraceColor {
+    fn new(cs: &Colorspace, color: &[f32], alpha: f32) -> Self {
+        Self {
+            colorspace: cs.name().to_string(),
+            components: color.to_vec(),
+            alpha,
+        }
+    }
+}
+
+/// A glyph of a traced text span, `origin` is in text space
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceGlyph {
+    pub gid: i32,
+    /// Unicode value of the glyph, `-1` if it has none
+    pub ucs: i32,
+    pub origin: Point,
+}
+
+/// A run of glyphs sharing the same font and transformation
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceSpan {
+    pub font: String,
+    pub trm: Matrix,
+    pub wmode: WriteMode,
+    pub glyphs: Vec<TraceGlyph>,
+}
+
+impl TraceSpan {
+    /// The unicode text of the span
+    pub fn text(&self) -> String {
+        self.glyphs
+            .iter()
+            .filter_map(|g| char::from_u32(g.ucs as u32))
+            .collect()
+    }
+}
+
+fn trace_spans(text: &Text) -> Vec<TraceSpan> {
+    text.spans()
+        .map(|span| TraceSpan {
+            font: span.font().name().to_string(),
+            trm: span.trm(),
+            wmode: span.wmode(),
+            glyphs: span
+                .items()
+                .map(|item| TraceGlyph {
+                    gid: item.gid(),
+                    ucs: item.ucs(),
+                    origin: Point::new(item.x(), item.y()),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// A single call made on a device.
+///
+/// Path segments are recorded untransformed, `ctm` maps them to device space.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    FillPath {
+        segments: Vec<DrawingSegment>,
+        even_odd: bool,
+        ctm: Matrix,
+        color: TraceColor,
+    },
+    StrokePath {
+        segments: Vec<DrawingSegment>,
+        stroke: DrawingStroke,
+        ctm: Matrix,
+        color: TraceColor,
+    },
+    ClipPath {
+        segments: Vec<DrawingSegment>,
+        even_odd: bool,
+        ctm: Matrix,
+        scissor: Rect,
+    },
+    ClipStrokePath {
+        segments: Vec<DrawingSegment>,
+        stroke: DrawingStroke,
+        ctm: Matrix,
+        scissor: Rect,
+    },
+    FillText {
+        spans: Vec<TraceSpan>,
+        ctm: Matrix,
+        color: TraceColor,
+    },
+    StrokeText {
+        spans: Vec<TraceSpan>,
+        stroke: DrawingStroke,
+        ctm: Matrix,
+        color: TraceColor,
+    },
+    ClipText {
+        spans: Vec<TraceSpan>,
+        ctm: Matrix,
+        scissor: Rect,
+    },
+    ClipStrokeText {
+        spans: Vec<TraceSpan>,
+        stroke: DrawingStroke,
+        ctm: Matrix,
+        scissor: Rect,
+    },
+    IgnoreText {
+        spans: Vec<TraceSpan>,
+        ctm: Matrix,
+    },
+    FillShade {
+        ctm: Matrix,
+        alpha: f32,
+    },
+    FillImage {
+        width: u32,
+        height: u32,
+        colorspace: String,
+        ctm: Matrix,
+        alpha: f32,
+    },
+    FillImageMask {
+        width: u32,
+        height: u32,
+        ctm: Matrix,
+        color: TraceColor,
+    },
+    ClipImageMask {
+        width: u32,
+        height: u32,
+        ctm: Matrix,
+        scissor: Rect,
+    },
+    PopClip,
+    BeginMask {
+        area: Rect,
+        luminosity: bool,
+        colorspace: Option<String>,
+        backdrop: Vec<f32>,
+    },
+    EndMask,
+    BeginGroup {
+        area: Rect,
+        colorspace: Option<String>,
+        isolated: bool,
+        knockout: bool,
+        blend_mode: BlendMode,
+        alpha: f32,
+    },
+    EndGroup,
+    BeginTile {
+        area: Rect,
+        view: Rect,
+        xstep: f32,
+        ystep: f32,
+        ctm: Matrix,
+        id: i32,
+    },
+    EndTile,
+    BeginLayer {
+        name: String,
+    },
+    EndLayer,
+}
+
+#[derive(Debug, Default)]
+struct Recording {
+    events: Vec<DeviceEvent>,
+    error: Option<Error>,
+}
+
+struct Recorder(Rc<RefCell<Recording>>);
+
+impl Recorder {
+    fn push(&mut self, event: DeviceEvent) {
+        self.0.borrow_mut().events.push(event);
+    }
+
+    fn segments(&mut self, path: &Path) -> Vec<DrawingSegment> {
+        path_segments(path, &Matrix::IDENTITY).unwrap_or_else(|err| {
+            self.0.borrow_mut().error.get_or_insert(err);
+            Vec::new()
+        })
+    }
+}
+
+impl NativeDevice for Recorder {
+    fn fill_path(
+        &mut self,
+        path: &Path,
+        even_odd: bool,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        _cp: ColorParams,
+    ) {
+        let segments = self.segments(path);
+        self.push(DeviceEvent::FillPath {
+            segments,
+            even_odd,
+            ctm: ctm.clone(),
+            color: TraceColor::new(cs, color, alpha),
+        });
+    }
+
+    fn stroke_path(
+        &mut self,
+        path: &Path,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        _cp: ColorParams,
+    ) {
+        let segments = self.segments(path);
+        self.push(DeviceEvent::StrokePath {
+            segments,
+            stroke: stroke.into(),
+            ctm: ctm.clone(),
+            color: TraceColor::new(cs, color, alpha),
+        });
+    }
+
+    fn clip_path(&mut self, path: &Path, even_odd: bool, ctm: &Matrix, scissor: Rect) {
+        let segments = self.segments(path);
+        self.push(DeviceEvent::ClipPath {
+            segments,
+            even_odd,
+            ctm: ctm.clone(),
+            scissor,
+        });
+    }
+
+    fn clip_stroke_path(&mut self, path: &Path, stroke: &StrokeState, ctm: &Matrix, scissor: Rect) {
+        let segments = self.segments(path);
+        self.push(DeviceEvent::ClipStrokePath {
+            segments,
+            stroke: stroke.into(),
+            ctm: ctm.clone(),
+            scissor,
+        });
+    }
+
+    fn fill_text(
+        &mut self,
+        text: &Text,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        _cp: ColorParams,
+    ) {
+        self.push(DeviceEvent::FillText {
+            spans: trace_spans(text),
+            ctm: ctm.clone(),
+            color: TraceColor::new(cs, color, alpha),
+        });
+    }
+
+    fn stroke_text(
+        &mut self,
+        text: &Text,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        _cp: ColorParams,
+    ) {
+        self.push(DeviceEvent::StrokeText {
+            spans: trace_spans(text),
+            stroke: stroke.into(),
+            ctm: ctm.clone(),
+            color: TraceColor::new(cs, color, alpha),
+        });
+    }
+
+    fn clip_text(&mut self, text: &Text, ctm: &Matrix, scissor: Rect) {
+        self.push(DeviceEvent::ClipText {
+            spans: trace_spans(text),
+            ctm: ctm.clone(),
+            scissor,
+        });
+    }
+
+    fn clip_stroke_text(&mut self, text: &Text, stroke: &StrokeState, ctm: &Matrix, scissor: Rect) {
+        self.push(DeviceEvent::ClipStrokeText {
+            spans: trace_spans(text),
+            stroke: stroke.into(),
+            ctm: ctm.clone(),
+            scissor,
+        });
+    }
+
+    fn ignore_text(&mut self, text: &Text, ctm: &Matrix) {
+        self.push(DeviceEvent::IgnoreText {
+            spans: trace_spans(text),
+            ctm: ctm.clone(),
+        });
+    }
+
+    fn fill_shade(&mut self, _shade: &Shade, ctm: &Matrix, alpha: f32, _cp: ColorParams) {
+        self.push(DeviceEvent::FillShade {
+            ctm: ctm.clone(),
+            alpha,
+        });
+    }
+
+    fn fill_image(&mut self, image: &Image, ctm: &Matrix, alpha: f32, _cp: ColorParams) {
+        self.push(DeviceEvent::FillImage {
+            width: image.width(),
+            height: image.height(),
+            colorspace: image.color_space().name().to_string(),
+            ctm: ctm.clone(),
+            alpha,
+        });
+    }
+
+    fn fill_image_mask(
+        &mut self,
+        image: &Image,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        _cp: ColorParams,
+    ) {
+        self.push(DeviceEvent::FillImageMask {
+            width: image.width(),
+            height: image.height(),
+            ctm: ctm.clone(),
+            color: TraceColor::new(cs, color, alpha),
+        });
+    }
+
+    fn clip_image_mask(&mut self, image: &Image, ctm: &Matrix, scissor: Rect) {
+        self.push(DeviceEvent::ClipImageMask {
+            width: image.width(),
+            height: image.height(),
+            ctm: ctm.clone(),
+            scissor,
+        });
+    }
+
+    fn pop_clip(&mut self) {
+        self.push(DeviceEvent::PopClip);
+    }
+
+    fn begin_mask(
+        &mut self,
+        area: Rect,
+        luminosity: bool,
+        cs: Option<&Colorspace>,
+        bc: &[f32],
+        _cp: ColorParams,
+    ) {
+        self.push(DeviceEvent::BeginMask {
+            area,
+            luminosity,
+            colorspace: cs.map(|cs| cs.name().to_string()),
+            backdrop: bc.to_vec(),
+        });
+    }
+
+    fn end_mask(&mut self) {
+        self.push(DeviceEvent::EndMask);
+    }
+
+    fn begin_group(
+        &mut self,
+        area: Rect,
+        cs: Option<&Colorspace>,
+        isolated: bool,
+        knockout: bool,
+        blend_mode: BlendMode,
+        alpha: f32,
+    ) {
+        self.push(DeviceEvent::BeginGroup {
+            area,
+            colorspace: cs.map(|cs| cs.name().to_string()),
+            isolated,
+            knockout,
+            blend_mode,
+            alpha,
+        });
+    }
+
+    fn end_group(&mut self) {
+        self.push(DeviceEvent::EndGroup);
+    }
+
+    fn begin_tile(
+        &mut self,
+        area: Rect,
+        view: Rect,
+        xstep: f32,
+        ystep: f32,
+        ctm: &Matrix,
+        id: i32,
+    ) -> bool {
+        self.push(DeviceEvent::BeginTile {
+            area,
+            view,
+            xstep,
+            ystep,
+            ctm: ctm.clone(),
+            id,
+        });
+        false
+    }
+
+    fn end_tile(&mut self) {
+        self.push(DeviceEvent::EndTile);
+    }
+
+    fn begin_layer(&mut self, name: &str) {
+        self.push(DeviceEvent::BeginLayer {
+            name: name.to_string(),
+        });
+    }
+
+    fn end_layer(&mut self) {
+        self.push(DeviceEvent::EndLayer);
+    }
+}
+
+/// A device that records every call made on it as a [`DeviceEvent`]
+///
+/// Run a page or display list against it and call [`TraceDevice::into_events`]
+/// to inspect what was drawn.
+#[derive(Debug)]
+pub struct TraceDevice {
+    device: Device,
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl TraceDevice {
+    pub fn new() -> Result<Self, Error> {
+        let recording = Rc::new(RefCell::new(Recording::default()));
+        let device = Device::from_native(Recorder(recording.clone()))?;
+        Ok(Self { device, recording })
+    }
+
+    /// Close the device and return the recorded events
+    pub fn into_events(self) -> Result<Vec<DeviceEvent>, Error> {
+        let Self { device, recording } = self;
+        drop(device);
+        let recording = recording.take();
+        match recording.error {
+            Some(err) => Err(err),
+            None => Ok(recording.events),
+        }
+    }
+}
+
+impl Deref for TraceDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Self::Target {
+        &self.device
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DeviceEvent, TraceDevice};
+    use crate::{Document, Matrix};
+
+    #[test]
+    fn test_trace_device() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let device = TraceDevice::new().unwrap();
+        page0.run(&device, &Matrix::IDENTITY).unwrap();
+        let events = device.into_events().unwrap();
+
+        let text: String = events
+            .iter()
+            .filter_map(|event| match event {
+                DeviceEvent::FillText { spans, .. } => Some(spans),
+                _ => None,
+            })
+            .flatten()
+            .map(|span| span.text())
+            .collect();
+        assert!(text.contains("Dummy"));
+    }
+}
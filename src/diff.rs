@@ -0,0 +1,323 @@
+use std::borrow::Cow;
+
+use crate::{ColorParams, Colorspace, Error, IRect, Page, Pixmap, RenderOptions};
+
+/// Options for comparing pixmaps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffOptions {
+    threshold: u8,
+    region_gap: u32,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0,
+            region_gap: 8,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Largest per-component difference that still counts as unchanged
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    pub fn set_threshold(&mut self, threshold: u8) -> &mut Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Changed pixels with fewer than this many unchanged pixels between them, both
+    /// horizontally and vertically, end up in the same region
+    pub fn region_gap(&self) -> u32 {
+        self.region_gap
+    }
+
+    pub fn set_region_gap(&mut self, gap: u32) -> &mut Self {
+        self.region_gap = gap;
+        self
+    }
+}
+
+/// Result of comparing two pixmaps
+#[derive(Debug)]
+pub struct PixmapDiff {
+    /// RGB image of the compared area, changed pixels are red on a faded copy of the original
+    pub pixmap: Pixmap,
+    pub changed_pixels: usize,
+    pub total_pixels: usize,
+    /// Peak signal-to-noise ratio in dB, infinite if both are identical
+    pub psnr: f64,
+    /// Bounding boxes of clusters of changed pixels
+    pub regions: Vec<IRect>,
+}
+
+impl PixmapDiff {
+    pub fn is_identical(&self) -> bool {
+        self.changed_pixels == 0
+    }
+}
+
+/// RGB pixmap, converted if necessary
+fn to_rgb(pixmap: &Pixmap) -> Result<Cow<'_, Pixmap>, Error> {
+    match pixmap.color_space() {
+        Some(cs) if cs.is_rgb() && pixmap.n() as u32 == 3 + pixmap.alpha() as u32 => {
+            Ok(Cow::Borrowed(pixmap))
+        }
+        _ => Ok(Cow::Owned(
+            pixmap.convert(&Colorspace::device_rgb(), ColorParams::default())?,
+        )),
+    }
+}
+
+/// Read access to the samples of an RGB pixmap
+struct RgbView<'a> {
+    rect: IRect,
+    n: usize,
+    stride: usize,
+    alpha: bool,
+    samples: &'a [u8],
+}
+
+impl<'a> RgbView<'a> {
+    fn new(pixmap: &'a Pixmap) -> Self {
+        Self {
+            rect: pixmap.rect(),
+            n: pixmap.n() as usize,
+            stride: pixmap.stride() as usize,
+            alpha: pixmap.alpha(),
+            samples: pixmap.samples(),
+        }
+    }
+
+    /// Color at absolute position `(x, y)` composited over white, white outside the pixmap
+    fn get(&self, x: i32, y: i32) -> [u8; 3] {
+        let rect = self.rect;
+        if x < rect.x0 || x >= rect.x1 || y < rect.y0 || y >= rect.y1 {
+            return [0xff; 3];
+        }
+        let offset = (y - rect.y0) as usize * self.stride + (x - rect.x0) as usize * self.n;
+        let pixel = &self.samples[offset..offset + self.n];
+        // Samples are premultiplied, so adding the missing coverage blends over white
+        let blank = if self.alpha { 0xff - pixel[3] } else { 0 };
+        [
+            pixel[0].saturating_add(blank),
+            pixel[1].saturating_add(blank),
+            pixel[2].saturating_add(blank),
+        ]
+    }
+}
+
+/// Unchanged pixels between two boxes along each axis, zero if they touch or overlap
+fn separation(a: IRect, b: IRect) -> (i32, i32) {
+    let x = (b.x0 - a.x1).max(a.x0 - b.x1).max(0);
+    let y = (b.y0 - a.y1).max(a.y0 - b.y1).max(0);
+    (x, y)
+}
+
+/// Merge the tight boxes of neighboring grid cells into regions
+///
+/// Cells are `gap` pixels wide, so two pixels less than `gap` apart are always in the
+/// same or in neighboring cells. Neighbors are only joined if their boxes are closer
+/// than `gap` as well.
+fn cluster(cells: &[Option<IRect>], columns: usize, gap: i32) -> Vec<IRect> {
+    let rows = if columns == 0 {
+        0
+    } else {
+        cells.len() / columns
+    };
+    let mut seen = vec![false; cells.len()];
+    let mut regions = Vec::new();
+    for start in 0..cells.len() {
+        if seen[start] || cells[start].is_none() {
+            continue;
+        }
+        seen[start] = true;
+        let mut region = cells[start].unwrap();
+        let mut stack = vec![start];
+        while let Some(index) = stack.pop() {
+            let (cx, cy) = ((index % columns) as isize, (index / columns) as isize);
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nx, ny) = (cx + dx, cy + dy);
+                    if nx < 0 || ny < 0 || nx >= columns as isize || ny >= rows as isize {
+                        continue;
+                    }
+                    let neighbor = ny as usize * columns + nx as usize;
+                    let Some(rect) = cells[neighbor] else {
+                        continue;
+                    };
+                    let (sx, sy) = separation(cells[index].unwrap(), rect);
+                    if !seen[neighbor] && sx < gap && sy < gap {
+                        seen[neighbor] = true;
+                        region.union(rect);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+        regions.push(region);
+    }
+    regions
+}
+
+impl Pixmap {
+    /// Compare with `other` pixel by pixel
+    ///
+    /// Both pixmaps are compared in RGB over a white background at their absolute
+    /// positions, so pixmaps of different sizes compare the union of their areas.
+    pub fn diff(&self, other: &Pixmap, options: &DiffOptions) -> Result<PixmapDiff, Error> {
+        let (a, b) = (to_rgb(self)?, to_rgb(other)?);
+        let (a, b) = (RgbView::new(&a), RgbView::new(&b));
+        let mut area = a.rect;
+        area.union(b.rect);
+        let mut pixmap = Pixmap::new_with_rect(&Colorspace::device_rgb(), area, false)?;
+
+        let gap = options.region_gap.max(1) as i32;
+        let width = (area.x1 - area.x0) as usize;
+        let height = (area.y1 - area.y0) as usize;
+        let columns = width.div_ceil(gap as usize);
+        let rows = height.div_ceil(gap as usize);
+        let mut cells: Vec<Option<IRect>> = vec![None; columns * rows];
+
+        let stride = pixmap.stride() as usize;
+        let samples = pixmap.samples_mut();
+        let mut changed_pixels = 0;
+        let mut squared_error = 0u64;
+        for y in area.y0..area.y1 {
+            let row = (y - area.y0) as usize * stride;
+            for x in area.x0..area.x1 {
+                let (pa, pb) = (a.get(x, y), b.get(x, y));
+                let mut changed = false;
+                for (ca, cb) in pa.iter().zip(&pb) {
+                    let delta = (*ca as i32 - *cb as i32).unsigned_abs();
+                    squared_error += (delta * delta) as u64;
+                    changed |= delta > options.threshold as u32;
+                }
+                let offset = row + (x - area.x0) as usize * 3;
+                let out = &mut samples[offset..offset + 3];
+                if changed {
+                    changed_pixels += 1;
+                    out.copy_from_slice(&[0xff, 0, 0]);
+                    let cell =
+                        ((y - area.y0) / gap) as usize * columns + ((x - area.x0) / gap) as usize;
+                    let pixel = IRect::new(x, y, x + 1, y + 1);
+                    cells[cell].get_or_insert(pixel).union(pixel);
+                } else {
+                    let gray = (pa[0] as u32 * 3 + pa[1] as u32 * 6 + pa[2] as u32) / 10;
+                    out.fill((0xff - (0xff - gray) / 4) as u8);
+                }
+            }
+        }
+
+        let total_pixels = width * height;
+        let psnr = if squared_error == 0 {
+            f64::INFINITY
+        } else {
+            let mse = squared_error as f64 / (total_pixels * 3) as f64;
+            10.0 * (255.0 * 255.0 / mse).log10()
+        };
+        Ok(PixmapDiff {
+            pixmap,
+            changed_pixels,
+            total_pixels,
+            psnr,
+            regions: cluster(&cells, columns, gap),
+        })
+    }
+}
+
+impl Page {
+    /// Render this page and `other` with the same `render` options and compare them,
+    /// see [`Pixmap::diff`]
+    pub fn diff(
+        &self,
+        other: &Page,
+        render: &RenderOptions,
+        options: &DiffOptions,
+    ) -> Result<PixmapDiff, Error> {
+        let a = self.to_pixmap_with_options(render)?;
+        let b = other.to_pixmap_with_options(render)?;
+        a.diff(&b, options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DiffOptions;
+    use crate::{Colorspace, Document, IRect, Pixmap, RenderOptions};
+
+    #[test]
+    fn test_pixmap_diff() {
+        let rgb = Colorspace::device_rgb();
+        let mut a = Pixmap::new_with_w_h(&rgb, 40, 40, false).unwrap();
+        a.clear_with(0xff).unwrap();
+        let mut b = a.clone();
+
+        let diff = a.diff(&b, &DiffOptions::default()).unwrap();
+        assert!(diff.is_identical());
+        assert!(diff.psnr.is_infinite());
+        assert!(diff.regions.is_empty());
+
+        // Two changes far apart and one just next to the first
+        let stride = b.stride() as usize;
+        for (x, y) in [(2, 2), (4, 3), (35, 30)] {
+            let offset = y * stride + x * 3;
+            b.samples_mut()[offset..offset + 3].copy_from_slice(&[0, 0, 0]);
+        }
+        let diff = a.diff(&b, &DiffOptions::default()).unwrap();
+        assert_eq!(diff.changed_pixels, 3);
+        assert_eq!(diff.total_pixels, 1600);
+        assert!(diff.psnr.is_finite());
+        assert_eq!(
+            diff.regions,
+            vec![IRect::new(2, 2, 5, 4), IRect::new(35, 30, 36, 31)]
+        );
+        assert_eq!(
+            &diff.pixmap.samples()[(2 * stride + 6)..][..3],
+            &[0xff, 0, 0]
+        );
+
+        let mut options = DiffOptions::default();
+        options.set_threshold(0xff);
+        assert!(a.diff(&b, &options).unwrap().is_identical());
+
+        // Neighboring cells only merge changes that are closer than the gap
+        let mut c = a.clone();
+        for (x, y) in [(0, 10), (7, 10), (15, 20), (16, 20)] {
+            let offset = y * stride + x * 3;
+            c.samples_mut()[offset..offset + 3].copy_from_slice(&[0, 0, 0]);
+        }
+        let mut options = DiffOptions::default();
+        options.set_region_gap(4);
+        assert_eq!(
+            a.diff(&c, &options).unwrap().regions,
+            vec![
+                IRect::new(0, 10, 1, 11),
+                IRect::new(7, 10, 8, 11),
+                IRect::new(15, 20, 17, 21)
+            ]
+        );
+
+        // A gray pixmap of a different size is compared over white
+        let mut gray = Pixmap::new_with_w_h(&Colorspace::device_gray(), 20, 20, false).unwrap();
+        gray.clear_with(0xff).unwrap();
+        let diff = a.diff(&gray, &DiffOptions::default()).unwrap();
+        assert!(diff.is_identical());
+        assert_eq!(diff.pixmap.rect(), IRect::new(0, 0, 40, 40));
+    }
+
+    #[test]
+    fn test_page_diff() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let mut render = RenderOptions::default();
+        render.set_dpi(36.0);
+        let diff = page0
+            .diff(&page0, &render, &DiffOptions::default())
+            .unwrap();
+        assert!(diff.is_identical());
+    }
+}
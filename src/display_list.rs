@@ -1,13 +1,75 @@
 use std::ffi::CString;
 use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 use mupdf_sys::*;
 
+use crate::error::MuPdfError;
 use crate::{
-    context, Colorspace, Cookie, Device, Error, Image, Matrix, Pixmap, Quad, Rect, TextPage,
+    context, Colorspace, Cookie, Device, Error, IRect, Image, Matrix, Pixmap, Quad, Rect, TextPage,
     TextPageOptions,
 };
 
+fn aborted() -> Error {
+    Error::MuPdf(MuPdfError {
+        code: FZ_ERROR_ABORT as i32,
+        message: "rendering aborted".to_string(),
+    })
+}
+
+/// Options for rendering a display list as a grid of tiles on multiple threads
+#[derive(Debug, Clone, Copy)]
+pub struct TileOptions<'a> {
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Number of worker threads, `0` uses the available parallelism
+    pub threads: usize,
+    /// Shared by all tiles, aborting it skips the tiles that have not been rendered yet
+    pub cookie: Option<&'a Cookie>,
+}
+
+impl Default for TileOptions<'_> {
+    fn default() -> Self {
+        Self {
+            tile_width: 512,
+            tile_height: 512,
+            threads: 0,
+            cookie: None,
+        }
+    }
+}
+
+// Colorspaces are immutable once created and reference counted under MuPDF's locks
+struct SharedColorspace(*mut fz_colorspace);
+
+impl SharedColorspace {
+    fn get(&self) -> Colorspace {
+        unsafe { Colorspace::from_raw(fz_keep_colorspace(context(), self.0)) }
+    }
+}
+
+unsafe impl Send for SharedColorspace {}
+unsafe impl Sync for SharedColorspace {}
+
+struct SharedPixmap(Pixmap);
+
+unsafe impl Send for SharedPixmap {}
+
+fn split_tiles(bbox: IRect, width: u32, height: u32) -> Vec<IRect> {
+    let (width, height) = (width.max(1) as usize, height.max(1) as usize);
+    let mut tiles = Vec::new();
+    for y in (bbox.y0..bbox.y1).step_by(height) {
+        for x in (bbox.x0..bbox.x1).step_by(width) {
+            let x1 = (x as i64 + width as i64).min(bbox.x1 as i64) as i32;
+            let y1 = (y as i64 + height as i64).min(bbox.y1 as i64) as i32;
+            tiles.push(IRect::new(x, y, x1, y1));
+        }
+    }
+    tiles
+}
+
 #[derive(Debug)]
 pub struct DisplayList {
     pub(crate) inner: *mut fz_display_list,
@@ -90,6 +152,118 @@ impl DisplayList {
         Ok(())
     }
 
+    fn device_bbox(&self, ctm: &Matrix) -> IRect {
+        unsafe { fz_round_rect(fz_transform_rect(self.bounds().into(), ctm.into())) }.into()
+    }
+
+    /// Render the display list as a grid of tiles on multiple threads
+    ///
+    /// `f` is called from the worker threads with each finished tile, the origin of
+    /// the tile pixmap is its position in the fully rendered page.
+    /// Returns an error if the cookie in `options` was aborted, even if some tiles were rendered.
+    pub fn render_tiles<F>(
+        &self,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        alpha: bool,
+        options: &TileOptions,
+        f: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(Pixmap) -> Result<(), Error> + Sync,
+    {
+        let tiles = split_tiles(
+            self.device_bbox(ctm),
+            options.tile_width,
+            options.tile_height,
+        );
+        let threads = match options.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let cs = SharedColorspace(cs.inner);
+        let next = AtomicUsize::new(0);
+        let error = Mutex::new(None);
+
+        let render_tile = |rect: IRect| -> Result<(), Error> {
+            let mut pixmap = Pixmap::new_with_rect(&cs.get(), rect, alpha)?;
+            if alpha {
+                pixmap.clear()?;
+            } else {
+                pixmap.clear_with(0xff)?;
+            }
+            {
+                let device = Device::from_pixmap(&pixmap)?;
+                match options.cookie {
+                    Some(cookie) => self.run_with_cookie(&device, ctm, rect.into(), cookie)?,
+                    None => self.run(&device, ctm, rect.into())?,
+                }
+            }
+            f(pixmap)
+        };
+        let worker = || loop {
+            if options.cookie.is_some_and(Cookie::aborted) {
+                break;
+            }
+            if error.lock().unwrap().is_some() {
+                break;
+            }
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(&rect) = tiles.get(index) else {
+                break;
+            };
+            if let Err(err) = render_tile(rect) {
+                error.lock().unwrap().get_or_insert(err);
+            }
+        };
+        thread::scope(|scope| {
+            for _ in 0..threads.min(tiles.len()) {
+                scope.spawn(&worker);
+            }
+        });
+
+        match error.into_inner().unwrap() {
+            Some(err) => Err(err),
+            None if options.cookie.is_some_and(Cookie::aborted) => Err(aborted()),
+            None => Ok(()),
+        }
+    }
+
+    /// Render the display list on multiple threads and stitch the tiles into one pixmap
+    pub fn to_pixmap_tiled(
+        &self,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        alpha: bool,
+        options: &TileOptions,
+    ) -> Result<Pixmap, Error> {
+        let bbox = self.device_bbox(ctm);
+        let mut dest = Pixmap::new_with_rect(cs, bbox, alpha)?;
+        if alpha {
+            dest.clear()?;
+        } else {
+            dest.clear_with(0xff)?;
+        }
+        let pixmap = Mutex::new(SharedPixmap(dest));
+        self.render_tiles(ctm, cs, alpha, options, |tile| {
+            let mut dest = pixmap.lock().unwrap();
+            let dest = &mut dest.0;
+            let n = dest.n() as usize;
+            let dest_stride = dest.stride() as usize;
+            let tile_stride = tile.stride() as usize;
+            let row_len = tile.width() as usize * n;
+            let x = (tile.x() - bbox.x0) as usize;
+            let y = (tile.y() - bbox.y0) as usize;
+            let samples = dest.samples_mut();
+            for (row, src) in tile.samples().chunks(tile_stride).enumerate() {
+                let start = (y + row) * dest_stride + x * n;
+                samples[start..start + row_len].copy_from_slice(&src[..row_len]);
+            }
+            Ok(())
+        })?;
+        Ok(pixmap.into_inner().unwrap().0)
+    }
+
     pub fn is_empty(&self) -> bool {
         unsafe { fz_display_list_is_empty(context(), self.inner) > 0 }
     }
@@ -178,6 +352,40 @@ mod test {
         assert_eq!(hits.len(), 0);
     }
 
+    #[test]
+    fn test_display_list_to_pixmap_tiled() {
+        use super::TileOptions;
+        use crate::{Colorspace, Cookie, Matrix};
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let list = page0.to_display_list(false).unwrap();
+        let ctm = Matrix::new_scale(2.0, 2.0);
+        let cs = Colorspace::device_rgb();
+
+        let options = TileOptions {
+            tile_width: 100,
+            tile_height: 150,
+            threads: 4,
+            cookie: None,
+        };
+        let tiled = list.to_pixmap_tiled(&ctm, &cs, false, &options).unwrap();
+        let whole = list.to_pixmap(&ctm, &cs, false).unwrap();
+        assert_eq!(tiled.rect(), whole.rect());
+        assert_eq!(tiled.samples(), whole.samples());
+
+        let cookie = Cookie::new().unwrap();
+        cookie.abort();
+        let options = TileOptions {
+            cookie: Some(&cookie),
+            ..options
+        };
+        assert!(list.to_pixmap_tiled(&ctm, &cs, false, &options).is_err());
+        assert!(list
+            .render_tiles(&ctm, &cs, false, &options, |_| Ok(()))
+            .is_err());
+    }
+
     #[test]
     fn test_multi_threaded_display_list_search() {
         use crossbeam_utils::thread;
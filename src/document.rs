@@ -5,7 +5,7 @@ use std::ptr;
 use mupdf_sys::*;
 
 use crate::pdf::PdfDocument;
-use crate::{context, Buffer, Colorspace, Cookie, Error, Outline, Page};
+use crate::{context, Buffer, Colorspace, Cookie, DefaultColorspaces, Error, Outline, Page};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MetadataName {
@@ -234,10 +234,22 @@ impl Document {
             if inner.is_null() {
                 return Ok(None);
             }
+            fz_keep_colorspace(context(), inner);
             Ok(Some(Colorspace::from_raw(inner)))
         }
     }
 
+    /// Default colorspaces for rendering this document, including its output intent
+    ///
+    /// Pass them to [`RenderOptions::set_default_colorspaces`](crate::RenderOptions::set_default_colorspaces)
+    /// to render as the document would be printed on its intended output device.
+    pub fn default_colorspaces(&self) -> Result<DefaultColorspaces, Error> {
+        unsafe {
+            let inner = ffi_try!(mupdf_document_default_colorspaces(context(), self.inner));
+            Ok(DefaultColorspaces::from_raw(inner))
+        }
+    }
+
     unsafe fn walk_outlines(&self, outline: *mut fz_outline) -> Vec<Outline> {
         let mut outlines = Vec::new();
         let mut next = outline;
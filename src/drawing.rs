@@ -0,0 +1,339 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ColorParams, Colorspace, Error, LineCap, LineJoin, Matrix, NativeDevice, Path, PathWalker,
+    Point, Rect, StrokeState,
+};
+
+/// Whether a drawing was filled or stroked
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DrawingKind {
+    Fill,
+    Stroke,
+}
+
+/// A single path segment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DrawingSegment {
+    MoveTo(Point),
+    LineTo(Point),
+    CurveTo(Point, Point, Point),
+    Close,
+}
+
+/// Owned copy of the `StrokeState` a path was stroked with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawingStroke {
+    pub line_width: f32,
+    pub start_cap: LineCap,
+    pub dash_cap: LineCap,
+    pub end_cap: LineCap,
+    pub line_join: LineJoin,
+    pub miter_limit: f32,
+    pub dash_phase: f32,
+    pub dashes: Vec<f32>,
+}
+
+impl From<&StrokeState> for DrawingStroke {
+    fn from(stroke: &StrokeState) -> Self {
+        Self {
+            line_width: stroke.line_width(),
+            start_cap: stroke.start_cap(),
+            dash_cap: stroke.dash_cap(),
+            end_cap: stroke.end_cap(),
+            line_join: stroke.line_join(),
+            miter_limit: stroke.miter_limit(),
+            dash_phase: stroke.dash_phase(),
+            dashes: stroke.dashes(),
+        }
+    }
+}
+
+/// A filled or stroked path found on a page.
+///
+/// A path that is both filled and stroked is reported twice, once per operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Drawing {
+    pub kind: DrawingKind,
+    /// Outline of the path, already transformed to page space
+    pub segments: Vec<DrawingSegment>,
+    /// Fill color converted to RGB, set for `DrawingKind::Fill`
+    pub fill_color: Option<[f32; 3]>,
+    /// Stroke color converted to RGB, set for `DrawingKind::Stroke`
+    pub stroke_color: Option<[f32; 3]>,
+    pub stroke: Option<DrawingStroke>,
+    pub opacity: f32,
+    pub even_odd: bool,
+    /// Area covered by the drawing. Fill bounds follow curves exactly, stroke bounds
+    /// are computed by MuPDF and include the line width, joins and curve control points.
+    pub bounds: Rect,
+}
+
+struct SegmentWalker<'a> {
+    ctm: &'a Matrix,
+    segments: Vec<DrawingSegment>,
+}
+
+impl SegmentWalker<'_> {
+    fn point(&self, x: f32, y: f32) -> Point {
+        Point::new(x, y).transform(self.ctm)
+    }
+}
+
+impl PathWalker for SegmentWalker<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.segments.push(DrawingSegment::MoveTo(p));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.segments.push(DrawingSegment::LineTo(p));
+    }
+
+    fn curve_to(&mut self, cx1: f32, cy1: f32, cx2: f32, cy2: f32, ex: f32, ey: f32) {
+        let c1 = self.point(cx1, cy1);
+        let c2 = self.point(cx2, cy2);
+        let e = self.point(ex, ey);
+        self.segments.push(DrawingSegment::CurveTo(c1, c2, e));
+    }
+
+    fn close(&mut self) {
+        self.segments.push(DrawingSegment::Close);
+    }
+}
+
+/// Flatten `path` into owned segments, transformed by `ctm`
+pub(crate) fn path_segments(path: &Path, ctm: &Matrix) -> Result<Vec<DrawingSegment>, Error> {
+    let mut walker = SegmentWalker {
+        ctm,
+        segments: Vec::new(),
+    };
+    path.walk(&mut walker)?;
+    Ok(walker.segments)
+}
+
+/// Parameters in `(0, 1)` where a cubic Bézier has a local extreme on one axis
+fn cubic_extremes(p0: f32, p1: f32, p2: f32, p3: f32) -> Vec<f32> {
+    // The derivative divided by 3 is `a * t^2 + b * t + c`
+    let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+    let b = 2.0 * (p0 - 2.0 * p1 + p2);
+    let c = p1 - p0;
+    let roots = if a.abs() < f32::EPSILON {
+        if b.abs() < f32::EPSILON {
+            vec![]
+        } else {
+            vec![-c / b]
+        }
+    } else {
+        let disc = b * b - 4.0 * a * c;
+        if disc < 0.0 {
+            vec![]
+        } else {
+            let sqrt = disc.sqrt();
+            vec![(-b + sqrt) / (2.0 * a), (-b - sqrt) / (2.0 * a)]
+        }
+    };
+    roots.into_iter().filter(|t| *t > 0.0 && *t < 1.0).collect()
+}
+
+fn cubic_point(p0: Point, p1: Point, p2: Point, p3: Point, t: f32) -> Point {
+    let mt = 1.0 - t;
+    let (w0, w1, w2, w3) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+    Point::new(
+        w0 * p0.x + w1 * p1.x + w2 * p2.x + w3 * p3.x,
+        w0 * p0.y + w1 * p1.y + w2 * p2.y + w3 * p3.y,
+    )
+}
+
+/// Exact bounds of the area covered by `segments`, curves are bounded by their extremes
+/// rather than their control points
+fn segments_bounds(segments: &[DrawingSegment]) -> Rect {
+    let mut bounds: Option<Rect> = None;
+    let mut add = |p: Point| {
+        let r = bounds.get_or_insert(Rect::new(p.x, p.y, p.x, p.y));
+        r.x0 = r.x0.min(p.x);
+        r.y0 = r.y0.min(p.y);
+        r.x1 = r.x1.max(p.x);
+        r.y1 = r.y1.max(p.y);
+    };
+    let (mut start, mut current) = (None, None);
+    for seg in segments {
+        match *seg {
+            DrawingSegment::MoveTo(p) => {
+                add(p);
+                start = Some(p);
+                current = Some(p);
+            }
+            DrawingSegment::LineTo(p) => {
+                add(p);
+                current = Some(p);
+            }
+            DrawingSegment::CurveTo(c1, c2, e) => {
+                add(e);
+                if let Some(s) = current {
+                    let mut ts = cubic_extremes(s.x, c1.x, c2.x, e.x);
+                    ts.extend(cubic_extremes(s.y, c1.y, c2.y, e.y));
+                    for t in ts {
+                        add(cubic_point(s, c1, c2, e, t));
+                    }
+                }
+                current = Some(e);
+            }
+            DrawingSegment::Close => current = start,
+        }
+    }
+    bounds.unwrap_or_default()
+}
+
+#[derive(Default)]
+pub(crate) struct DrawingCollector {
+    pub(crate) drawings: Vec<Drawing>,
+    pub(crate) error: Option<Error>,
+}
+
+impl DrawingCollector {
+    fn segments(&mut self, path: &Path, ctm: &Matrix) -> Vec<DrawingSegment> {
+        path_segments(path, ctm).unwrap_or_else(|err| {
+            self.error.get_or_insert(err);
+            Vec::new()
+        })
+    }
+
+    fn rgb(&mut self, cs: &Colorspace, color: &[f32], cp: ColorParams) -> [f32; 3] {
+        match cs.convert_color(&Colorspace::device_rgb(), color, cp) {
+            Ok(rgb) => [rgb[0], rgb[1], rgb[2]],
+            Err(err) => {
+                self.error.get_or_insert(err);
+                [0.0; 3]
+            }
+        }
+    }
+}
+
+/// Adapter that feeds a shared `DrawingCollector` from a `NativeDevice`
+pub(crate) struct DrawingDevice(pub(crate) Rc<RefCell<DrawingCollector>>);
+
+impl NativeDevice for DrawingDevice {
+    fn fill_path(
+        &mut self,
+        path: &Path,
+        even_odd: bool,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+        let mut collector = self.0.borrow_mut();
+        let segments = collector.segments(path, ctm);
+        let fill_color = collector.rgb(cs, color, cp);
+        let bounds = segments_bounds(&segments);
+        collector.drawings.push(Drawing {
+            kind: DrawingKind::Fill,
+            segments,
+            fill_color: Some(fill_color),
+            stroke_color: None,
+            stroke: None,
+            opacity: alpha,
+            even_odd,
+            bounds,
+        });
+    }
+
+    fn stroke_path(
+        &mut self,
+        path: &Path,
+        stroke: &StrokeState,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        color: &[f32],
+        alpha: f32,
+        cp: ColorParams,
+    ) {
+        let mut collector = self.0.borrow_mut();
+        let segments = collector.segments(path, ctm);
+        let stroke_color = collector.rgb(cs, color, cp);
+        let bounds = match path.bounds(stroke, ctm) {
+            Ok(bounds) => bounds,
+            Err(err) => {
+                collector.error.get_or_insert(err);
+                segments_bounds(&segments)
+            }
+        };
+        collector.drawings.push(Drawing {
+            kind: DrawingKind::Stroke,
+            segments,
+            fill_color: None,
+            stroke_color: Some(stroke_color),
+            stroke: Some(stroke.into()),
+            opacity: alpha,
+            even_odd: false,
+            bounds,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{segments_bounds, DrawingCollector, DrawingDevice, DrawingKind, DrawingSegment};
+    use crate::{ColorParams, Colorspace, Device, Matrix, Path, Point, Rect};
+
+    #[test]
+    fn test_collect_fill_path() {
+        let collector = Rc::new(RefCell::new(DrawingCollector::default()));
+        let device = Device::from_native(DrawingDevice(collector.clone())).unwrap();
+
+        let mut path = Path::new().unwrap();
+        path.move_to(10.0, 10.0).unwrap();
+        path.line_to(20.0, 10.0).unwrap();
+        path.line_to(20.0, 30.0).unwrap();
+        path.close().unwrap();
+        device
+            .fill_path(
+                &path,
+                false,
+                &Matrix::new_translate(5.0, 0.0),
+                &Colorspace::device_gray(),
+                &[0.0],
+                0.5,
+                ColorParams::default(),
+            )
+            .unwrap();
+        drop(device);
+
+        let collector = collector.take();
+        assert!(collector.error.is_none());
+        assert_eq!(collector.drawings.len(), 1);
+        let drawing = &collector.drawings[0];
+        assert_eq!(drawing.kind, DrawingKind::Fill);
+        assert_eq!(
+            drawing.segments[0],
+            DrawingSegment::MoveTo(Point::new(15.0, 10.0))
+        );
+        assert_eq!(drawing.segments.last(), Some(&DrawingSegment::Close));
+        assert_eq!(drawing.fill_color, Some([0.0, 0.0, 0.0]));
+        assert_eq!(drawing.opacity, 0.5);
+        assert_eq!(drawing.bounds, Rect::new(15.0, 10.0, 25.0, 30.0));
+    }
+
+    #[test]
+    fn test_curve_bounds() {
+        // The control points reach y = 10, the curve itself only y = 7.5
+        let segments = [
+            DrawingSegment::MoveTo(Point::new(0.0, 0.0)),
+            DrawingSegment::CurveTo(
+                Point::new(0.0, 10.0),
+                Point::new(10.0, 10.0),
+                Point::new(10.0, 0.0),
+            ),
+        ];
+        assert_eq!(segments_bounds(&segments), Rect::new(0.0, 0.0, 10.0, 7.5));
+    }
+}
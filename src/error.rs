@@ -1,6 +1,6 @@
+use std::ffi::NulError;
 use std::fmt;
 use std::io;
-use std::ffi::NulError;
 
 use mupdf_sys::*;
 
@@ -57,6 +57,8 @@ macro_rules! ffi_try {
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
+    /// An argument is out of range or inconsistent with the object it is used with
+    InvalidArgument(String),
     InvalidLanguage(String),
     InvalidPdfDocument,
     MuPdf(MuPdfError),
@@ -67,6 +69,7 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::Io(ref err) => err.fmt(f),
+            Error::InvalidArgument(ref msg) => write!(f, "invalid argument: {}", msg),
             Error::InvalidLanguage(ref lang) => write!(f, "invalid language {}", lang),
             Error::InvalidPdfDocument => write!(f, "invalid pdf document"),
             Error::MuPdf(ref err) => err.fmt(f),
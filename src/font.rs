@@ -4,6 +4,7 @@ use std::str::FromStr;
 
 use mupdf_sys::*;
 use num_enum::TryFromPrimitive;
+use serde::{Deserialize, Serialize};
 
 use crate::{context, Buffer, Error, Matrix, Path};
 
@@ -15,7 +16,7 @@ pub enum SimpleFontEncoding {
     Cyrillic,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive, Serialize, Deserialize)]
 #[repr(u32)]
 pub enum WriteMode {
     Horizontal = 0,
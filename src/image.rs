@@ -54,7 +54,10 @@ impl Image {
     }
 
     pub fn color_space(&self) -> Colorspace {
-        unsafe { Colorspace::from_raw((*self.inner).colorspace) }
+        unsafe {
+            let ptr = fz_keep_colorspace(context(), (*self.inner).colorspace);
+            Colorspace::from_raw(ptr)
+        }
     }
 
     pub fn resolution(&self) -> (i32, i32) {
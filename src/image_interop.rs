@@ -0,0 +1,150 @@
+use std::convert::TryFrom;
+
+use ::image::{DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+
+use crate::{ColorParams, Colorspace, Error, Image, Pixmap};
+
+impl Pixmap {
+    /// Convert to an `image::DynamicImage` with straight (non-premultiplied) alpha
+    ///
+    /// Gray and RGB pixmaps keep their colorspace, everything else (including spot
+    /// channels) is converted to RGB. Pixmaps without a colorspace become a gray
+    /// image of their alpha channel.
+    pub fn to_dynamic_image(&self) -> Result<DynamicImage, Error> {
+        let converted;
+        let pixmap = match self.color_space() {
+            Some(cs)
+                if !(cs.is_gray() || cs.is_rgb())
+                    || self.n() as u32 != cs.n() + self.alpha() as u32 =>
+            {
+                converted = self.convert(&Colorspace::device_rgb(), ColorParams::default())?;
+                &converted
+            }
+            _ => self,
+        };
+
+        let (width, height) = (pixmap.width(), pixmap.height());
+        let n = pixmap.n() as usize;
+        let row_len = width as usize * n;
+        let stride = pixmap.stride() as usize;
+        let mut data = Vec::with_capacity(row_len * height as usize);
+        for row in pixmap.samples().chunks(stride) {
+            data.extend_from_slice(&row[..row_len]);
+        }
+        if pixmap.alpha() && n > 1 {
+            for pixel in data.chunks_mut(n) {
+                let (color, alpha) = pixel.split_at_mut(n - 1);
+                let alpha = alpha[0] as u32;
+                if alpha != 0 && alpha != 255 {
+                    for c in color {
+                        *c = ((*c as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                    }
+                }
+            }
+        }
+
+        let image = match (n, pixmap.alpha()) {
+            (1, _) => GrayImage::from_raw(width, height, data).map(DynamicImage::ImageLuma8),
+            (2, true) => {
+                GrayAlphaImage::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+            }
+            (3, false) => RgbImage::from_raw(width, height, data).map(DynamicImage::ImageRgb8),
+            (4, true) => RgbaImage::from_raw(width, height, data).map(DynamicImage::ImageRgba8),
+            _ => None,
+        };
+        image.ok_or_else(|| {
+            Error::InvalidArgument(format!("unsupported pixmap layout with {} components", n))
+        })
+    }
+
+    /// Create a pixmap from an `image::DynamicImage`
+    ///
+    /// Gray images become DeviceGray pixmaps, everything else DeviceRGB. Alpha is kept
+    /// and premultiplied as MuPDF expects. Higher bit depths are reduced to 8 bits.
+    pub fn from_dynamic_image(image: &DynamicImage) -> Result<Self, Error> {
+        let color = image.color();
+        let alpha = color.has_alpha();
+        let (cs, data) = match (color.has_color(), alpha) {
+            (false, false) => (Colorspace::device_gray(), image.to_luma8().into_raw()),
+            (false, true) => (Colorspace::device_gray(), image.to_luma_alpha8().into_raw()),
+            (true, false) => (Colorspace::device_rgb(), image.to_rgb8().into_raw()),
+            (true, true) => (Colorspace::device_rgb(), image.to_rgba8().into_raw()),
+        };
+        let mut pixmap =
+            Pixmap::new_with_w_h(&cs, image.width() as i32, image.height() as i32, alpha)?;
+        let row_len = image.width() as usize * pixmap.n() as usize;
+        let stride = pixmap.stride() as usize;
+        if row_len > 0 {
+            for (dst, src) in pixmap
+                .samples_mut()
+                .chunks_mut(stride)
+                .zip(data.chunks(row_len))
+            {
+                dst[..row_len].copy_from_slice(src);
+            }
+        }
+        pixmap.premultiply()?;
+        Ok(pixmap)
+    }
+}
+
+impl Image {
+    /// Create an image from an `image::DynamicImage`, see [`Pixmap::from_dynamic_image`]
+    pub fn from_dynamic_image(image: &DynamicImage) -> Result<Self, Error> {
+        let pixmap = Pixmap::from_dynamic_image(image)?;
+        Image::from_pixmap(&pixmap)
+    }
+}
+
+impl TryFrom<&Pixmap> for DynamicImage {
+    type Error = Error;
+
+    fn try_from(pixmap: &Pixmap) -> Result<Self, Self::Error> {
+        pixmap.to_dynamic_image()
+    }
+}
+
+impl TryFrom<&DynamicImage> for Pixmap {
+    type Error = Error;
+
+    fn try_from(image: &DynamicImage) -> Result<Self, Self::Error> {
+        Pixmap::from_dynamic_image(image)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::image::{DynamicImage, Rgba, RgbaImage};
+
+    use crate::{Colorspace, Image, Pixmap};
+
+    #[test]
+    fn test_pixmap_to_dynamic_image() {
+        let mut pixmap = Pixmap::new_with_w_h(&Colorspace::device_cmyk(), 4, 2, false).unwrap();
+        pixmap.clear().unwrap();
+        let image = pixmap.to_dynamic_image().unwrap();
+        let rgb = image.as_rgb8().unwrap();
+        assert_eq!(rgb.dimensions(), (4, 2));
+        assert!(rgb.pixels().all(|p| p.0.iter().all(|&c| c > 0xf0)));
+    }
+
+    #[test]
+    fn test_pixmap_from_dynamic_image() {
+        let mut rgba = RgbaImage::new(3, 2);
+        rgba.put_pixel(0, 0, Rgba([200, 100, 0, 128]));
+        let image = DynamicImage::ImageRgba8(rgba);
+
+        let pixmap = Pixmap::from_dynamic_image(&image).unwrap();
+        assert!(pixmap.alpha());
+        assert_eq!((pixmap.width(), pixmap.height()), (3, 2));
+        assert_eq!(&pixmap.samples()[..4], &[100, 50, 0, 128]);
+
+        let back = pixmap.to_dynamic_image().unwrap();
+        let pixel = back.as_rgba8().unwrap().get_pixel(0, 0);
+        assert!((pixel[0] as i32 - 200).abs() <= 1);
+        assert_eq!(pixel[3], 128);
+
+        let image = Image::from_dynamic_image(&image).unwrap();
+        assert_eq!(image.width(), 3);
+    }
+}
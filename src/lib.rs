@@ -2,6 +2,8 @@
 
 /// Error types
 #[rustfmt::skip] #[macro_use] pub mod error;
+/// Streaming band writers
+pub mod band_writer;
 /// Bitmaps used for creating halftoned versions of contone buffers, and saving out
 pub mod bitmap;
 /// Dynamically allocated array of bytes
@@ -18,24 +20,32 @@ pub mod cookie;
 pub mod destination;
 /// Device interface
 pub mod device;
+/// Visual comparison of pixmaps and pages
+pub mod diff;
 /// A way of packaging up a stream of graphical operations
 pub mod display_list;
 /// Common document operation interface
 pub mod document;
 /// Easy creation of new documents
 pub mod document_writer;
+/// Vector drawings extracted from a page
+pub mod drawing;
 /// Font
 pub mod font;
 /// Glyph
 pub mod glyph;
 /// Image
 pub mod image;
+/// Conversions to and from the `image` crate
+#[cfg(feature = "image")]
+mod image_interop;
 /// Hyperlink
 pub mod link;
 /// Matrix operations
 pub mod matrix;
 /// Outline
 pub mod outline;
+mod output;
 /// Document page
 pub mod page;
 /// Path type
@@ -50,6 +60,8 @@ pub mod point;
 pub mod quad;
 /// Rectangle types
 pub mod rect;
+/// Options for rendering pages
+pub mod render_options;
 /// Separations
 pub mod separations;
 /// Shadings
@@ -58,27 +70,37 @@ pub mod shade;
 pub mod size;
 /// Stroke state
 pub mod stroke_state;
+/// Owned model of structured text
+pub mod structured_text;
 
 /// System font loading
 #[cfg(feature = "system-fonts")]
 pub mod system_font;
+/// Table detection
+pub mod table;
 /// Text objects
 pub mod text;
 /// Text page
 pub mod text_page;
 
-pub use bitmap::Bitmap;
+pub use band_writer::BandFormat;
+pub use bitmap::{Bitmap, BitmapFormat, Halftone};
 pub use buffer::Buffer;
 pub use color_params::{ColorParams, RenderingIntent};
-pub use colorspace::Colorspace;
+pub use colorspace::{Colorspace, DefaultColorspaces};
 pub(crate) use context::context;
 pub use context::Context;
 pub use cookie::Cookie;
 pub use destination::{Destination, DestinationKind};
-pub use device::{BlendMode, Device};
-pub use display_list::DisplayList;
+pub use device::{
+    BBoxDevice, BlendMode, ColorUsage, ContentBounds, Device, DeviceEvent, DeviceFilter,
+    NativeDevice, TestDevice, TestDeviceOptions, TraceColor, TraceDevice, TraceGlyph, TraceSpan,
+};
+pub use diff::{DiffOptions, PixmapDiff};
+pub use display_list::{DisplayList, TileOptions};
 pub use document::{Document, MetadataName};
 pub use document_writer::DocumentWriter;
+pub use drawing::{Drawing, DrawingKind, DrawingSegment, DrawingStroke};
 pub(crate) use error::ffi_error;
 pub use error::Error;
 pub use font::{CjkFontOrdering, Font, SimpleFontEncoding, WriteMode};
@@ -89,13 +111,19 @@ pub use matrix::Matrix;
 pub use outline::Outline;
 pub use page::Page;
 pub use path::{Path, PathWalker};
-pub use pixmap::{ImageFormat, Pixmap};
+pub use pixmap::{DeskewBorder, ImageFormat, ImageWriteOptions, Pixmap, PixmapRef};
 pub use point::Point;
 pub use quad::Quad;
 pub use rect::{IRect, Rect};
-pub use separations::Separations;
+pub use render_options::RenderOptions;
+pub use separations::{Plate, SeparationBehavior, Separations};
 pub use shade::Shade;
 pub use size::Size;
 pub use stroke_state::{LineCap, LineJoin, StrokeState};
+pub use structured_text::StructuredText;
+pub use table::{Table, TableCell, TableOptions};
 pub use text::{Text, TextItem, TextSpan};
-pub use text_page::{TextBlock, TextChar, TextLine, TextPage, TextPageOptions};
+pub use text_page::{
+    SelectionMode, TextBlock, TextChar, TextCharFlags, TextLine, TextPage, TextPageOptions,
+    TextRun, TextWord,
+};
@@ -1,9 +1,10 @@
 use std::f32::consts::PI;
 
 use mupdf_sys::*;
+use serde::{Deserialize, Serialize};
 
 /// A row-major 3x3 matrix used for representing transformations of coordinates
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Matrix {
     pub a: f32,
     pub b: f32,
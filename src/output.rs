@@ -0,0 +1,132 @@
+use std::any::Any;
+use std::io::{self, Write};
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use mupdf_sys::*;
+
+use crate::{context, ffi_error, Error};
+
+struct WriteState<'a> {
+    w: &'a mut dyn Write,
+    error: Option<io::Error>,
+    /// Panic of the writer, resumed once control is back in Rust
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+unsafe extern "C" fn write_output(
+    ctx: *mut fz_context,
+    state: *mut c_void,
+    data: *const c_void,
+    n: usize,
+) {
+    let state = &mut *(state as *mut WriteState);
+    if state.error.is_some() || state.panic.is_some() || n == 0 {
+        return;
+    }
+    let data = slice::from_raw_parts(data as *const u8, n);
+    // Unwinding through MuPDF's C frames is undefined behavior
+    match panic::catch_unwind(AssertUnwindSafe(|| state.w.write_all(data))) {
+        Ok(Ok(())) => return,
+        Ok(Err(err)) => state.error = Some(err),
+        Err(payload) => state.panic = Some(payload),
+    }
+    // Stop MuPDF from producing more output, the error itself is reported by `Output`
+    mupdf_throw(ctx, b"write error\0".as_ptr() as *const c_char);
+}
+
+/// An `fz_output` forwarding everything written to it to a `Write`
+///
+/// The first write error aborts the MuPDF operation writing to the output and is
+/// reported instead of the resulting MuPDF error.
+pub(crate) struct Output<'a> {
+    pub(crate) inner: *mut fz_output,
+    state: Box<WriteState<'a>>,
+    closed: bool,
+}
+
+impl<'a> Output<'a> {
+    fn new(w: &'a mut dyn Write) -> Result<Self, Error> {
+        let mut state = Box::new(WriteState {
+            w,
+            error: None,
+            panic: None,
+        });
+        let inner = unsafe {
+            ffi_try!(mupdf_new_output(
+                context(),
+                &mut *state as *mut WriteState as *mut c_void,
+                Some(write_output)
+            ))
+        };
+        Ok(Self {
+            inner,
+            state,
+            closed: false,
+        })
+    }
+
+    /// Run `f` with an output writing to `w`, then flush and close it
+    pub(crate) fn write_with<F>(w: &'a mut dyn Write, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(*mut fz_output) -> Result<(), Error>,
+    {
+        let mut out = Output::new(w)?;
+        let result = f(out.inner);
+        out.resume_panic();
+        if let Some(err) = out.state.error.take() {
+            return Err(err.into());
+        }
+        result?;
+        out.close()
+    }
+
+    /// Continue a panic of the writer caught while MuPDF was writing
+    fn resume_panic(&mut self) {
+        if let Some(payload) = self.state.panic.take() {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Flush the output and report the first write error, if any
+    fn close(mut self) -> Result<(), Error> {
+        self.closed = true;
+        let mut err = ptr::null_mut();
+        unsafe {
+            mupdf_close_output(context(), self.inner, &mut err);
+        }
+        // Flushing may still reach the writer, its panic or error wins over MuPDF's error
+        self.resume_panic();
+        if let Some(io_err) = self.state.error.take() {
+            if !err.is_null() {
+                unsafe { mupdf_drop_error(err) };
+            }
+            return Err(io_err.into());
+        }
+        if !err.is_null() {
+            return Err(unsafe { ffi_error(err) }.into());
+        }
+        self.state.w.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Output<'_> {
+    fn drop(&mut self) {
+        if self.inner.is_null() {
+            return;
+        }
+        unsafe {
+            if !self.closed {
+                let mut err = ptr::null_mut();
+                mupdf_close_output(context(), self.inner, &mut err);
+                if !err.is_null() {
+                    mupdf_drop_error(err);
+                }
+            }
+            fz_drop_output(context(), self.inner);
+        }
+    }
+}
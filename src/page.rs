@@ -1,15 +1,22 @@
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
-use std::io::Read;
+use std::io::{Read, Write};
 use std::ptr;
+use std::rc::Rc;
 use std::slice;
 
 use serde::{Deserialize, Serialize};
 
 use mupdf_sys::*;
 
+use crate::device::{content_bounds, is_color};
+use crate::drawing::{DrawingCollector, DrawingDevice};
+use crate::output::Output;
+use crate::separations::split_plates;
 use crate::{
-    context, Buffer, Colorspace, Cookie, Device, DisplayList, Error, Link, Matrix, Pixmap, Quad,
-    Rect, Separations, TextPage, TextPageOptions,
+    context, BandFormat, Buffer, Colorspace, ContentBounds, Cookie, Device, DisplayList, Drawing,
+    Error, Link, Matrix, Pixmap, Plate, Quad, Rect, RenderOptions, Separations, TextPage,
+    TextPageOptions,
 };
 
 #[derive(Debug)]
@@ -31,6 +38,39 @@ impl Page {
         Ok(rect.into())
     }
 
+    /// Bounding box of the visible content of the page after applying `ctm`
+    ///
+    /// Unlike [`Page::bounds`] this is the actual ink extent, with separate extents
+    /// for text, images and vector graphics.
+    pub fn content_bounds(&self, ctm: &Matrix) -> Result<ContentBounds, Error> {
+        content_bounds(|device| self.run(device, ctm))
+    }
+
+    /// Check whether the page uses any color
+    ///
+    /// ## Params
+    ///
+    /// * `threshold` - how far (from 0.0 to 1.0) a color may deviate from gray before it counts as color
+    /// * `ignore_images` - only look at text and vector graphics
+    pub fn is_color(&self, threshold: f32, ignore_images: bool) -> Result<bool, Error> {
+        is_color(threshold, ignore_images, |device| {
+            self.run(device, &Matrix::IDENTITY)
+        })
+    }
+
+    /// Whether the page is blank, e.g. a separator sheet in a scanned batch
+    ///
+    /// The page is rendered in gray at a low resolution and counts as blank if at most
+    /// `threshold` (from 0.0 to 1.0) of its pixels are noticeably darker than white.
+    pub fn is_blank(&self, threshold: f32) -> Result<bool, Error> {
+        let gray = Colorspace::device_gray();
+        let mut options = RenderOptions::default();
+        options.set_dpi(36.0).set_colorspace(&gray);
+        let pixmap = self.to_pixmap_with_options(&options)?;
+        // Ignore faint scanner noise and paper tone
+        Ok(pixmap.ink_fraction(32) <= threshold)
+    }
+
     pub fn to_pixmap(
         &self,
         ctm: &Matrix,
@@ -51,6 +91,102 @@ impl Page {
         }
     }
 
+    /// Render the page to a pixmap as described by `options`
+    pub fn to_pixmap_with_options(&self, options: &RenderOptions) -> Result<Pixmap, Error> {
+        let ctm = options.ctm(self.bounds()?);
+        let rgb = Colorspace::device_rgb();
+        let cs = options.colorspace().unwrap_or(&rgb);
+        let background = options.background();
+        let cp: Option<fz_color_params> = options.color_params().map(Into::into);
+        unsafe {
+            let inner = ffi_try!(mupdf_page_to_pixmap_with_options(
+                context(),
+                self.inner,
+                ctm.into(),
+                cs.inner,
+                options.alpha(),
+                options.clip().into(),
+                background.as_ref().map_or(ptr::null(), |bg| bg.as_ptr()),
+                options
+                    .separations()
+                    .map_or(ptr::null_mut(), |seps| seps.inner),
+                options.annotations(),
+                options.widgets(),
+                options.aa_level().unwrap_or(-1),
+                cp.as_ref().map_or(ptr::null(), |cp| cp as *const _),
+                options
+                    .default_colorspaces()
+                    .map_or(ptr::null_mut(), |default_cs| default_cs.inner),
+                options.proof().map_or(ptr::null_mut(), |proof| proof.inner),
+                options
+                    .cookie()
+                    .map_or(ptr::null_mut(), |cookie| cookie.inner)
+            ));
+            Ok(Pixmap::from_raw(inner))
+        }
+    }
+
+    /// Render the page into one gray pixmap per printing plate
+    ///
+    /// The process plates (cyan, magenta, yellow and black) come first, followed by
+    /// every separation in `seps` whose behavior is [`SeparationBehavior::Spot`](crate::SeparationBehavior::Spot).
+    pub fn to_plates(&self, ctm: &Matrix, seps: &Separations) -> Result<Vec<Plate>, Error> {
+        let cmyk = Colorspace::device_cmyk();
+        let mut options = RenderOptions::default();
+        options
+            .set_ctm(ctm.clone())
+            .set_colorspace(&cmyk)
+            .set_separations(seps);
+        let pixmap = self.to_pixmap_with_options(&options)?;
+        split_plates(&pixmap, seps)
+    }
+
+    /// Render the page to RGB as it would look printed, honoring overprint
+    ///
+    /// The page is rendered in CMYK plus the spot colors of `seps` and then composited.
+    pub fn to_pixmap_simulate_overprint(
+        &self,
+        ctm: &Matrix,
+        seps: &Separations,
+    ) -> Result<Pixmap, Error> {
+        unsafe {
+            let inner = ffi_try!(mupdf_page_to_pixmap_simulate_overprint(
+                context(),
+                self.inner,
+                ctm.into(),
+                seps.inner
+            ));
+            Ok(Pixmap::from_raw(inner))
+        }
+    }
+
+    /// Render the page band by band and stream it encoded as `format` into `w`
+    ///
+    /// Only a single band of `band_height` rows is held in memory at a time.
+    pub fn write_bands<W: Write>(
+        &self,
+        w: &mut W,
+        format: BandFormat,
+        ctm: &Matrix,
+        cs: &Colorspace,
+        alpha: bool,
+        band_height: u32,
+    ) -> Result<(), Error> {
+        Output::write_with(w, |out| unsafe {
+            ffi_try!(mupdf_page_write_bands(
+                context(),
+                self.inner,
+                out,
+                format as i32,
+                ctm.into(),
+                cs.inner,
+                alpha,
+                band_height as i32
+            ));
+            Ok(())
+        })
+    }
+
     pub fn to_svg(&self, ctm: &Matrix) -> Result<String, Error> {
         let mut buf = unsafe {
             let inner = ffi_try!(mupdf_page_to_svg(
@@ -227,6 +363,22 @@ impl Page {
         Ok(())
     }
 
+    /// Extract the vector graphics of the page, in page coordinates
+    ///
+    /// Every filled or stroked path is returned as a separate [`Drawing`],
+    /// clip paths, text and images are skipped.
+    pub fn drawings(&self) -> Result<Vec<Drawing>, Error> {
+        let collector = Rc::new(RefCell::new(DrawingCollector::default()));
+        let device = Device::from_native(DrawingDevice(collector.clone()))?;
+        self.run(&device, &Matrix::IDENTITY)?;
+        drop(device);
+        let collector = collector.take();
+        match collector.error {
+            Some(err) => Err(err),
+            None => Ok(collector.drawings),
+        }
+    }
+
     pub fn to_html(&self) -> Result<String, Error> {
         let mut buf = unsafe {
             let inner = ffi_try!(mupdf_page_to_html(context(), self.inner));
@@ -416,13 +568,15 @@ pub struct Block {
 }
 
 // StructuredText
+/// Parsed output of [`Page::stext_page_as_json_from_page`], see
+/// [`TextPage::to_structured_text`] for a lossless model
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct StextPage {
     pub blocks: Vec<Block>,
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use crate::page::StextPage;
     use crate::{Document, Matrix};
 
@@ -528,12 +682,334 @@ mod test {
         assert_eq!(links.len(), 0);
     }
 
+    /// Single page PDF of 300x300 points with the given content stream, Helvetica is `/F1`
+    pub(crate) fn pdf_with_content(content: &str) -> Document {
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 300 300] /Contents 4 0 R \
+             /Resources << /Font << /F1 << /Type /Font /Subtype /Type1 /BaseFont /Helvetica >> >> >> >>"
+                .to_string(),
+            format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            ),
+        ];
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+        }
+        let xref = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            objects.len() + 1
+        ));
+        for offset in offsets {
+            pdf.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        pdf.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        ));
+        Document::from_bytes(pdf.as_bytes(), "pdf").unwrap()
+    }
+
+    #[test]
+    fn test_page_drawings() {
+        use crate::{DrawingKind, DrawingSegment, Point, Rect};
+
+        let doc = pdf_with_content("1 0 0 rg 10 20 30 40 re f 0 0 1 RG 2 w 100 100 m 200 100 l S");
+        let page0 = doc.load_page(0).unwrap();
+        let drawings = page0.drawings().unwrap();
+        assert_eq!(drawings.len(), 2);
+
+        // Page space has its origin at the top left
+        let fill = &drawings[0];
+        assert_eq!(fill.kind, DrawingKind::Fill);
+        assert_eq!(fill.fill_color, Some([1.0, 0.0, 0.0]));
+        assert_eq!(fill.stroke_color, None);
+        assert_eq!(fill.segments.len(), 5);
+        assert_eq!(
+            fill.segments[0],
+            DrawingSegment::MoveTo(Point::new(10.0, 280.0))
+        );
+        assert!(fill.segments[1..4]
+            .iter()
+            .all(|seg| matches!(seg, DrawingSegment::LineTo(_))));
+        assert_eq!(fill.segments[4], DrawingSegment::Close);
+        assert_eq!(fill.bounds, Rect::new(10.0, 240.0, 40.0, 280.0));
+
+        let stroke = &drawings[1];
+        assert_eq!(stroke.kind, DrawingKind::Stroke);
+        assert_eq!(stroke.stroke_color, Some([0.0, 0.0, 1.0]));
+        assert_eq!(stroke.stroke.as_ref().unwrap().line_width, 2.0);
+        assert_eq!(
+            stroke.segments,
+            [
+                DrawingSegment::MoveTo(Point::new(100.0, 200.0)),
+                DrawingSegment::LineTo(Point::new(200.0, 200.0))
+            ]
+        );
+        let bounds = stroke.bounds;
+        assert!(bounds.x0 <= 100.0 && bounds.x1 >= 200.0);
+        assert!(bounds.y0 <= 199.0 && bounds.y1 >= 201.0);
+
+        let json = serde_json::to_string(&drawings).unwrap();
+        let parsed: Vec<crate::Drawing> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, drawings);
+    }
+
+    #[test]
+    fn test_page_content_bounds() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let page_bounds = page0.bounds().unwrap();
+        let bounds = page0.content_bounds(&Matrix::IDENTITY).unwrap();
+        let text = bounds.text.unwrap();
+        assert!(text.x0 >= page_bounds.x0 && text.x1 <= page_bounds.x1);
+        assert!(text.y0 >= page_bounds.y0 && text.y1 <= page_bounds.y1);
+        assert!(bounds.images.is_none());
+        assert!(bounds.all.is_some());
+    }
+
+    #[test]
+    fn test_page_is_color() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        assert!(!page0.is_color(0.02, false).unwrap());
+        assert!(!page0.is_color(0.02, true).unwrap());
+
+        let doc = pdf_with_content("1 0 0 rg 10 10 100 100 re f");
+        let page0 = doc.load_page(0).unwrap();
+        assert!(page0.is_color(0.02, false).unwrap());
+        assert!(page0.is_color(0.02, true).unwrap());
+
+        let doc = pdf_with_content("0.5 g 10 10 100 100 re f");
+        let page0 = doc.load_page(0).unwrap();
+        assert!(!page0.is_color(0.02, false).unwrap());
+    }
+
+    #[test]
+    fn test_page_is_color_ignore_images() {
+        // A single red pixel as inline image on an otherwise gray page
+        let doc = pdf_with_content(
+            "0.5 g 10 10 100 100 re f \
+             q 100 0 0 100 150 150 cm BI /W 1 /H 1 /CS /RGB /BPC 8 /F /AHx ID FF0000> EI Q",
+        );
+        let page0 = doc.load_page(0).unwrap();
+        assert!(page0.is_color(0.02, false).unwrap());
+        assert!(!page0.is_color(0.02, true).unwrap());
+    }
+
+    #[test]
+    fn test_page_write_bands() {
+        use crate::BandFormat;
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let rgb = Colorspace::device_rgb();
+
+        let mut png = Vec::new();
+        page0
+            .write_bands(
+                &mut png,
+                BandFormat::PNG,
+                &Matrix::IDENTITY,
+                &rgb,
+                false,
+                64,
+            )
+            .unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+
+        let mut pam = Vec::new();
+        page0
+            .write_bands(&mut pam, BandFormat::PAM, &Matrix::IDENTITY, &rgb, true, 64)
+            .unwrap();
+        assert!(pam.starts_with(b"P7"));
+
+        let mut pwg = Vec::new();
+        page0
+            .write_bands(
+                &mut pwg,
+                BandFormat::PWG,
+                &Matrix::IDENTITY,
+                &rgb,
+                false,
+                64,
+            )
+            .unwrap();
+        assert!(pwg.starts_with(b"RaS2"));
+    }
+
+    #[test]
+    fn test_page_write_bands_error() {
+        use crate::{BandFormat, Error};
+        use std::io::{self, Write};
+
+        struct Failing(usize);
+
+        impl Write for Failing {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                self.0 += 1;
+                Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let mut out = Failing(0);
+        let err = page0
+            .write_bands(
+                &mut out,
+                BandFormat::PNM,
+                &Matrix::IDENTITY,
+                &Colorspace::device_rgb(),
+                false,
+                16,
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+        // Rendering stopped at the first failed write
+        assert_eq!(out.0, 1);
+    }
+
+    #[test]
+    fn test_page_write_bands_panic() {
+        use crate::BandFormat;
+        use std::io::{self, Write};
+        use std::panic::{self, AssertUnwindSafe};
+
+        struct Panicking;
+
+        impl Write for Panicking {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                panic!("writer panicked");
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        // The panic is carried across MuPDF and resumed in the caller
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+            page0.write_bands(
+                &mut Panicking,
+                BandFormat::PNM,
+                &Matrix::IDENTITY,
+                &Colorspace::device_rgb(),
+                false,
+                16,
+            )
+        }))
+        .unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"writer panicked"));
+    }
+
+    #[test]
+    fn test_page_to_pixmap_with_options() {
+        use crate::{IRect, RenderOptions};
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let bounds = page0.bounds().unwrap();
+
+        let mut options = RenderOptions::default();
+        options.set_dpi(144.0).set_annotations(false);
+        let pixmap = page0.to_pixmap_with_options(&options).unwrap();
+        assert_eq!(pixmap.width(), (bounds.width() * 2.0).ceil() as u32);
+        assert_eq!(pixmap.height(), (bounds.height() * 2.0).ceil() as u32);
+
+        options
+            .set_clip(IRect::new(0, 0, 10, 20))
+            .set_background([1.0, 0.0, 0.0]);
+        let pixmap = page0.to_pixmap_with_options(&options).unwrap();
+        assert_eq!(pixmap.width(), 10);
+        assert_eq!(pixmap.height(), 20);
+        assert_eq!(&pixmap.samples()[..3], &[0xff, 0, 0]);
+
+        let mut options = RenderOptions::default();
+        options.set_fit(100, 100).set_alpha(true);
+        let pixmap = page0.to_pixmap_with_options(&options).unwrap();
+        assert!(pixmap.alpha());
+        assert!(pixmap.width() <= 100 && pixmap.height() <= 100);
+    }
+
+    #[test]
+    fn test_page_to_pixmap_with_default_colorspaces() {
+        use crate::{Colorspace, RenderOptions};
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let mut default_cs = doc.default_colorspaces().unwrap();
+        assert!(default_cs.output_intent().is_none());
+
+        let cmyk = Colorspace::device_cmyk();
+        default_cs.set_output_intent(&cmyk);
+        let mut options = RenderOptions::default();
+        options
+            .set_default_colorspaces(&default_cs)
+            .set_proof(&cmyk)
+            .set_annotations(false);
+        let pixmap = page0.to_pixmap_with_options(&options).unwrap();
+        assert_eq!(pixmap.n(), 3);
+        assert!(pixmap.samples().iter().any(|&v| v != 0xff));
+    }
+
+    #[test]
+    fn test_page_is_blank() {
+        use crate::pdf::PdfDocument;
+        use crate::Size;
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        assert!(!page0.is_blank(0.0).unwrap());
+        assert!(page0.is_blank(1.0).unwrap());
+
+        let mut pdf = PdfDocument::new();
+        let blank = pdf.new_page(Size::A4).unwrap();
+        assert!(blank.is_blank(0.0).unwrap());
+    }
+
     #[test]
     fn test_page_separations() {
         let doc = Document::open("tests/files/dummy.pdf").unwrap();
         let page0 = doc.load_page(0).unwrap();
-        let seps = page0.separations().unwrap();
+        let mut seps = page0.separations().unwrap();
         assert_eq!(seps.len(), 0);
+        assert_eq!(seps.name(0), None);
+        assert_eq!(seps.behavior(0), None);
+        assert!(seps
+            .set_behavior(0, crate::SeparationBehavior::Spot)
+            .is_err());
+        assert!(seps.equivalent_rgb(0).is_err());
+    }
+
+    #[test]
+    fn test_page_to_plates() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let seps = page0.separations().unwrap();
+        let plates = page0.to_plates(&Matrix::IDENTITY, &seps).unwrap();
+        let names: Vec<&str> = plates.iter().map(|plate| plate.name.as_str()).collect();
+        assert_eq!(names, ["Cyan", "Magenta", "Yellow", "Black"]);
+        assert!(plates[3].pixmap.samples().iter().any(|&v| v != 0xff));
+
+        let pixmap = page0
+            .to_pixmap_simulate_overprint(&Matrix::IDENTITY, &seps)
+            .unwrap();
+        assert_eq!(pixmap.n(), 3);
     }
 
     #[test]
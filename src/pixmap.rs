@@ -1,10 +1,15 @@
 use std::ffi::CString;
-use std::io::{self, Write};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr;
 use std::slice;
 
 use mupdf_sys::*;
 
-use crate::{context, Buffer, Colorspace, Error, IRect};
+use crate::output::Output;
+use crate::{context, Buffer, ColorParams, Colorspace, Error, IRect, Point, Quad};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
@@ -14,6 +19,86 @@ pub enum ImageFormat {
     PAM = 2,
     PSD = 3,
     PS = 4,
+    JPEG = 5,
+    /// Halftoned to 1 bit gray
+    PBM = 6,
+    /// Halftoned to 1 bit CMYK
+    PKM = 7,
+    PCLm = 8,
+    PWG = 9,
+    /// JPEG 2000
+    JPX = 10,
+}
+
+impl ImageFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::PNG => "image/png",
+            ImageFormat::PNM => "image/x-portable-anymap",
+            ImageFormat::PAM => "image/x-portable-arbitrarymap",
+            ImageFormat::PSD => "image/vnd.adobe.photoshop",
+            ImageFormat::PS => "application/postscript",
+            ImageFormat::JPEG => "image/jpeg",
+            ImageFormat::PBM => "image/x-portable-bitmap",
+            ImageFormat::PKM => "application/octet-stream",
+            ImageFormat::PCLm => "application/pclm",
+            ImageFormat::PWG => "image/pwg-raster",
+            ImageFormat::JPX => "image/jp2",
+        }
+    }
+}
+
+/// How the size of a deskewed pixmap relates to the original
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum DeskewBorder {
+    /// Grow to keep every pixel of the original
+    Increase = 0,
+    /// Keep the original size, cropping the corners
+    Maintain = 1,
+    /// Shrink to drop the empty corners introduced by the rotation
+    Decrease = 2,
+}
+
+/// Encoder options for [`Pixmap::write_to_with_options`]
+///
+/// There is no PNG compression level: MuPDF's PNG writer always uses zlib's default level
+/// and doesn't expose a setting for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageWriteOptions {
+    quality: u8,
+    resolution: Option<(i32, i32)>,
+}
+
+impl Default for ImageWriteOptions {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            resolution: None,
+        }
+    }
+}
+
+impl ImageWriteOptions {
+    /// Quality from 0 to 100 for JPEG and JPX, 100 is lossless for JPX
+    pub fn quality(&self) -> u8 {
+        self.quality
+    }
+
+    pub fn set_quality(&mut self, quality: u8) -> &mut Self {
+        self.quality = quality.min(100);
+        self
+    }
+
+    /// Resolution in dpi stored in the image, `None` uses the resolution of the pixmap
+    pub fn resolution(&self) -> Option<(i32, i32)> {
+        self.resolution
+    }
+
+    pub fn set_resolution(&mut self, x_res: i32, y_res: i32) -> &mut Self {
+        self.resolution = Some((x_res, y_res));
+        self
+    }
 }
 
 /// Pixmaps (pixel maps) are objects at the heart of MuPDF’s rendering capabilities.
@@ -65,6 +150,76 @@ impl Pixmap {
         Self::new(cs, 0, 0, w, h, alpha)
     }
 
+    /// Create a pixmap with a copy of `samples`
+    ///
+    /// `samples` holds `h` rows of `stride` bytes, each starting with `w` pixels of
+    /// `cs.n()` color components followed by alpha if `alpha` is set. The copy is
+    /// tightly packed, so its stride may differ.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_samples(
+        cs: &Colorspace,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        alpha: bool,
+        stride: usize,
+        samples: &[u8],
+    ) -> Result<Self, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_new_pixmap_from_samples(
+                context(),
+                cs.inner,
+                x,
+                y,
+                w,
+                h,
+                alpha,
+                stride as _,
+                samples.as_ptr(),
+                samples.len()
+            ))
+        };
+        Ok(Self { inner })
+    }
+
+    /// Create a pixmap using `samples` as its pixel storage without copying
+    ///
+    /// The layout of `samples` is the same as for [`Pixmap::from_samples`]. Anything
+    /// drawn into the pixmap ends up in `samples`. Dropping the [`PixmapRef`] copies the
+    /// samples once, so anything still holding the pixmap, e.g. an [`Image`](crate::Image)
+    /// created from it, keeps valid pixels.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_samples_mut<'a>(
+        cs: &Colorspace,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        alpha: bool,
+        stride: usize,
+        samples: &'a mut [u8],
+    ) -> Result<PixmapRef<'a>, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_new_pixmap_with_data(
+                context(),
+                cs.inner,
+                x,
+                y,
+                w,
+                h,
+                alpha,
+                stride as _,
+                samples.as_mut_ptr(),
+                samples.len()
+            ))
+        };
+        Ok(PixmapRef {
+            pixmap: Self { inner },
+            marker: PhantomData,
+        })
+    }
+
     /// X-coordinate of top-left corner
     pub fn x(&self) -> i32 {
         unsafe { (*self.inner).x }
@@ -118,6 +273,7 @@ impl Pixmap {
             if ptr.is_null() {
                 return None;
             }
+            fz_keep_colorspace(context(), ptr);
             Some(Colorspace::from_raw(ptr))
         }
     }
@@ -143,12 +299,12 @@ impl Pixmap {
     }
 
     pub fn samples(&self) -> &[u8] {
-        let len = (self.width() * self.height() * self.n() as u32) as usize;
+        let len = self.height() as usize * self.stride() as usize;
         unsafe { slice::from_raw_parts((*self.inner).samples, len) }
     }
 
     pub fn samples_mut(&mut self) -> &mut [u8] {
-        let len = (self.width() * self.height() * self.n() as u32) as usize;
+        let len = self.height() as usize * self.stride() as usize;
         unsafe { slice::from_raw_parts_mut((*self.inner).samples, len) }
     }
 
@@ -228,6 +384,263 @@ impl Pixmap {
         Ok(())
     }
 
+    /// Convert to a new pixmap in another colorspace, keeping the alpha channel
+    pub fn convert(&self, cs: &Colorspace, cp: ColorParams) -> Result<Self, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_convert_pixmap(
+                context(),
+                self.inner,
+                cs.inner,
+                cp.into()
+            ))
+        };
+        Ok(Self { inner })
+    }
+
+    /// Scale to a new pixmap of `width` x `height` pixels with the same origin
+    ///
+    /// If `clip` is given, only that part of the scaled pixmap is produced.
+    pub fn scale(&self, width: u32, height: u32, clip: Option<IRect>) -> Result<Self, Error> {
+        let clip: Option<fz_irect> = clip.map(Into::into);
+        let inner = unsafe {
+            ffi_try!(mupdf_scale_pixmap(
+                context(),
+                self.inner,
+                width as f32,
+                height as f32,
+                clip.as_ref().map_or(ptr::null(), |clip| clip as *const _)
+            ))
+        };
+        Ok(Self { inner })
+    }
+
+    /// Shrink the pixmap in place by a factor of `2^factor` in each direction,
+    /// averaging the merged pixels
+    pub fn subsample(&mut self, factor: u32) -> Result<(), Error> {
+        unsafe {
+            ffi_try!(mupdf_subsample_pixmap(context(), self.inner, factor as _));
+        }
+        Ok(())
+    }
+
+    /// Copy of the pixmap with an opaque alpha channel added
+    pub fn with_alpha(&self) -> Result<Self, Error> {
+        let inner = unsafe { ffi_try!(mupdf_pixmap_set_alpha(context(), self.inner, true)) };
+        Ok(Self { inner })
+    }
+
+    /// Copy of the pixmap with the alpha channel removed
+    ///
+    /// Translucent pixels are composited over white, i.e. over no ink for subtractive
+    /// colorspaces such as CMYK.
+    pub fn without_alpha(&self) -> Result<Self, Error> {
+        let inner = unsafe { ffi_try!(mupdf_pixmap_set_alpha(context(), self.inner, false)) };
+        Ok(Self { inner })
+    }
+
+    /// Multiply color values by alpha, does nothing without an alpha channel
+    ///
+    /// Pixmaps rendered by MuPDF are already premultiplied.
+    pub fn premultiply(&mut self) -> Result<(), Error> {
+        unsafe {
+            ffi_try!(mupdf_premultiply_pixmap(context(), self.inner));
+        }
+        Ok(())
+    }
+
+    /// Divide color values by alpha, does nothing without an alpha channel
+    pub fn unpremultiply(&mut self) -> Result<(), Error> {
+        unsafe {
+            ffi_try!(mupdf_unpremultiply_pixmap(context(), self.inner));
+        }
+        Ok(())
+    }
+
+    /// Copy the pixels of `src` within `rect` into this pixmap
+    ///
+    /// `rect` is in absolute coordinates, both pixmaps are positioned by their origin.
+    pub fn copy_rect(&mut self, src: &Pixmap, rect: IRect) -> Result<(), Error> {
+        unsafe {
+            ffi_try!(mupdf_copy_pixmap_rect(
+                context(),
+                self.inner,
+                src.inner,
+                rect.into()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Paint the premultiplied `src` over this pixmap where they overlap
+    ///
+    /// Both pixmaps need the same colorspace. `opacity` ranges from 0.0 to 1.0.
+    /// Results are clamped, so an unpremultiplied `src` (see [`Pixmap::unpremultiply`])
+    /// comes out too bright but never wraps around.
+    pub fn composite(&mut self, src: &Pixmap, opacity: f32) -> Result<(), Error> {
+        unsafe {
+            ffi_try!(mupdf_composite_pixmap(
+                context(),
+                self.inner,
+                src.inner,
+                opacity
+            ));
+        }
+        Ok(())
+    }
+
+    /// Count how often each value occurs, one histogram per component including alpha
+    pub fn histogram(&self) -> Vec<[u32; 256]> {
+        let n = self.n() as usize;
+        let mut histograms = vec![[0u32; 256]; n];
+        for row in self.rows() {
+            for pixel in row.chunks(n) {
+                for (histogram, &value) in histograms.iter_mut().zip(pixel) {
+                    histogram[value as usize] += 1;
+                }
+            }
+        }
+        histograms
+    }
+
+    /// Fraction (0.0 to 1.0) of pixels that differ from the background
+    ///
+    /// The background is white, i.e. zero ink for subtractive colorspaces, or fully
+    /// transparent if the pixmap has alpha. A pixel only counts if one of its components
+    /// differs from the background by more than `threshold`, which absorbs scanner noise.
+    pub fn ink_fraction(&self, threshold: u8) -> f32 {
+        let total = self.width() as usize * self.height() as usize;
+        if total == 0 {
+            return 0.0;
+        }
+        let n = self.n() as usize;
+        let is_ink = self.ink_test(threshold);
+        let inked: usize = self
+            .rows()
+            .map(|row| row.chunks(n).filter(|pixel| is_ink(pixel)).count())
+            .sum();
+        inked as f32 / total as f32
+    }
+
+    /// Bounding box of the pixels that differ from the background, see [`Pixmap::ink_fraction`]
+    ///
+    /// The box is in the same coordinates as [`Pixmap::rect`], `None` if nothing is inked.
+    pub fn ink_bbox(&self, threshold: u8) -> Option<IRect> {
+        let n = self.n() as usize;
+        let is_ink = self.ink_test(threshold);
+        let mut bbox: Option<IRect> = None;
+        for (y, row) in self.rows().enumerate() {
+            let mut inked = row
+                .chunks(n)
+                .enumerate()
+                .filter(|(_, pixel)| is_ink(pixel))
+                .map(|(x, _)| x as i32);
+            let first = match inked.next() {
+                Some(x) => x,
+                None => continue,
+            };
+            let last = inked.last().unwrap_or(first);
+            let y = y as i32;
+            bbox = Some(match bbox {
+                Some(b) => IRect::new(b.x0.min(first), b.y0, b.x1.max(last + 1), y + 1),
+                None => IRect::new(first, y, last + 1, y + 1),
+            });
+        }
+        let (x, y) = self.origin();
+        bbox.map(|b| IRect::new(b.x0 + x, b.y0 + y, b.x1 + x, b.y1 + y))
+    }
+
+    /// Rows of pixel data without any padding at the end of the stride
+    fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        let row_len = self.width() as usize * self.n() as usize;
+        let stride = (self.stride() as usize).max(1);
+        self.samples()
+            .chunks(stride)
+            .map(move |row| &row[..row_len])
+    }
+
+    fn ink_test(&self, threshold: u8) -> impl Fn(&[u8]) -> bool {
+        let colors = self.n() as usize - self.alpha() as usize;
+        let alpha = self.alpha();
+        let (process, subtractive) = self
+            .color_space()
+            .map_or((0, false), |cs| (cs.n() as usize, cs.is_subtractive()));
+        move |pixel: &[u8]| {
+            if alpha {
+                return pixel[colors] > threshold;
+            }
+            // Spot channels always count ink, even in additive pixmaps
+            pixel.iter().enumerate().any(|(i, &c)| {
+                let ink = if i < process && !subtractive {
+                    255 - c
+                } else {
+                    c
+                };
+                ink > threshold
+            })
+        }
+    }
+
+    /// Detect the skew angle of scanned text or lines in degrees
+    ///
+    /// Pass the result to [`Pixmap::deskew`] to straighten the pixmap.
+    pub fn detect_skew(&self) -> Result<f32, Error> {
+        let degrees = unsafe { ffi_try!(mupdf_detect_skew(context(), self.inner)) };
+        Ok(degrees)
+    }
+
+    /// Rotate by `degrees` to undo the skew found by [`Pixmap::detect_skew`]
+    pub fn deskew(&self, degrees: f32, border: DeskewBorder) -> Result<Self, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_deskew_pixmap(
+                context(),
+                self.inner,
+                degrees,
+                border as i32
+            ))
+        };
+        Ok(Self { inner })
+    }
+
+    /// Find the corners of a document photographed on a background, in pixel coordinates
+    pub fn detect_document(&self) -> Result<Option<Quad>, Error> {
+        let origin: fz_point = Point::new(0.0, 0.0).into();
+        let mut quad = fz_quad {
+            ul: origin,
+            ur: origin,
+            ll: origin,
+            lr: origin,
+        };
+        let found = unsafe { ffi_try!(mupdf_detect_document(context(), self.inner, &mut quad)) };
+        Ok(if found { Some(quad.into()) } else { None })
+    }
+
+    /// Map the area inside `quad` onto a rectangular pixmap of `width` x `height`,
+    /// correcting its perspective
+    pub fn warp(&self, quad: &Quad, width: u32, height: u32) -> Result<Self, Error> {
+        let inner = unsafe {
+            ffi_try!(mupdf_warp_pixmap(
+                context(),
+                self.inner,
+                quad.clone().into(),
+                width as _,
+                height as _
+            ))
+        };
+        Ok(Self { inner })
+    }
+
+    /// Like [`Pixmap::warp`], sized from the average lengths of opposite edges of `quad`
+    pub fn dewarp(&self, quad: &Quad) -> Result<Self, Error> {
+        let dist = |a: &Point, b: &Point| (a.x - b.x).hypot(a.y - b.y);
+        let width = (dist(&quad.ul, &quad.ur) + dist(&quad.ll, &quad.lr)) / 2.0;
+        let height = (dist(&quad.ul, &quad.ll) + dist(&quad.ur, &quad.lr)) / 2.0;
+        self.warp(
+            quad,
+            width.round().max(1.0) as u32,
+            height.round().max(1.0) as u32,
+        )
+    }
+
     fn get_image_data(&self, format: ImageFormat) -> Result<Buffer, Error> {
         let buf = unsafe {
             let inner = ffi_try!(mupdf_pixmap_get_image_data(
@@ -245,6 +658,59 @@ impl Pixmap {
         Ok(io::copy(&mut buf, w)?)
     }
 
+    pub fn write_to_with_options<W: Write>(
+        &self,
+        w: &mut W,
+        format: ImageFormat,
+        options: &ImageWriteOptions,
+    ) -> Result<(), Error> {
+        let (x_res, y_res) = options.resolution().unwrap_or((0, 0));
+        Output::write_with(w, |out| unsafe {
+            ffi_try!(mupdf_write_pixmap(
+                context(),
+                out,
+                self.inner,
+                format as i32,
+                options.quality() as i32,
+                x_res,
+                y_res
+            ));
+            Ok(())
+        })
+    }
+
+    pub fn save_as_with_options(
+        &self,
+        filename: &str,
+        format: ImageFormat,
+        options: &ImageWriteOptions,
+    ) -> Result<(), Error> {
+        let mut file = File::create(filename)?;
+        self.write_to_with_options(&mut file, format, options)
+    }
+
+    /// Encode the pixmap as a base64 `data:` URI, e.g. for embedding in HTML
+    pub fn to_data_uri(
+        &self,
+        format: ImageFormat,
+        options: &ImageWriteOptions,
+    ) -> Result<String, Error> {
+        let c_mime = CString::new(format.mime_type())?;
+        let mut buf = unsafe {
+            let inner = ffi_try!(mupdf_pixmap_to_data_uri(
+                context(),
+                self.inner,
+                format as i32,
+                options.quality() as i32,
+                c_mime.as_ptr()
+            ));
+            Buffer::from_raw(inner)
+        };
+        let mut uri = String::new();
+        buf.read_to_string(&mut uri)?;
+        Ok(uri)
+    }
+
     pub fn try_clone(&self) -> Result<Self, Error> {
         let inner = unsafe { ffi_try!(mupdf_clone_pixmap(context(), self.inner)) };
         Ok(Self { inner })
@@ -265,9 +731,36 @@ impl Clone for Pixmap {
     }
 }
 
+/// A [`Pixmap`] drawing on borrowed samples, see [`Pixmap::from_samples_mut`]
+#[derive(Debug)]
+pub struct PixmapRef<'a> {
+    pixmap: Pixmap,
+    marker: PhantomData<&'a mut [u8]>,
+}
+
+impl Deref for PixmapRef<'_> {
+    type Target = Pixmap;
+
+    fn deref(&self) -> &Pixmap {
+        &self.pixmap
+    }
+}
+
+impl Drop for PixmapRef<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            mupdf_pixmap_detach_samples(context(), self.pixmap.inner);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{Colorspace, IRect, Pixmap};
+    use super::{Colorspace, DeskewBorder, IRect, ImageFormat, ImageWriteOptions, Pixmap};
+    use crate::ColorParams;
+    use crate::Image;
+    use crate::{Point, Quad};
+    use mupdf_sys::{FZ_VERSION_MAJOR, FZ_VERSION_MINOR};
 
     #[test]
     fn test_pixmap_properties() {
@@ -338,4 +831,254 @@ mod test {
         let pixels = pixmap.pixels();
         assert!(pixels.is_some());
     }
+
+    #[test]
+    fn test_pixmap_convert_and_scale() {
+        let rgb = Colorspace::device_rgb();
+        let mut pixmap = Pixmap::new_with_w_h(&rgb, 100, 50, false).unwrap();
+        pixmap.clear_with(0xff).unwrap();
+
+        let gray = pixmap
+            .convert(&Colorspace::device_gray(), ColorParams::default())
+            .unwrap();
+        assert_eq!(gray.n(), 1);
+        assert!(gray.samples().iter().all(|&v| v == 0xff));
+
+        let scaled = pixmap.scale(50, 25, None).unwrap();
+        assert_eq!((scaled.width(), scaled.height()), (50, 25));
+
+        let clipped = pixmap
+            .scale(50, 25, Some(IRect::new(0, 0, 10, 10)))
+            .unwrap();
+        assert_eq!((clipped.width(), clipped.height()), (10, 10));
+
+        pixmap.subsample(1).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (50, 25));
+    }
+
+    #[test]
+    fn test_pixmap_alpha() {
+        let rgb = Colorspace::device_rgb();
+        let mut pixmap = Pixmap::new_with_w_h(&rgb, 2, 1, false).unwrap();
+        pixmap
+            .samples_mut()
+            .copy_from_slice(&[200, 100, 0, 50, 50, 50]);
+
+        let mut rgba = pixmap.with_alpha().unwrap();
+        assert!(rgba.alpha());
+        assert_eq!(rgba.samples(), &[200, 100, 0, 255, 50, 50, 50, 255]);
+
+        rgba.samples_mut()[3] = 128;
+        rgba.premultiply().unwrap();
+        assert_eq!(&rgba.samples()[..4], &[100, 50, 0, 128]);
+        rgba.unpremultiply().unwrap();
+        assert!((rgba.samples()[0] as i32 - 200).abs() <= 1);
+
+        rgba.premultiply().unwrap();
+        let premultiplied = rgba.samples()[..3].to_vec();
+
+        // Translucent pixels end up over white, opaque ones keep their color
+        let rgb = rgba.without_alpha().unwrap();
+        assert!(!rgb.alpha());
+        assert_eq!(rgb.n(), 3);
+        let expected: Vec<u8> = premultiplied.iter().map(|&c| c + 127).collect();
+        assert_eq!(&rgb.samples()[..3], expected.as_slice());
+        assert_eq!(&rgb.samples()[3..], &[50, 50, 50]);
+    }
+
+    #[test]
+    fn test_pixmap_copy_and_composite() {
+        let rgb = Colorspace::device_rgb();
+        let mut dst = Pixmap::new_with_w_h(&rgb, 10, 10, false).unwrap();
+        dst.clear_with(0xff).unwrap();
+        let mut src = Pixmap::new(&rgb, 5, 5, 5, 5, false).unwrap();
+        src.clear_with(0).unwrap();
+
+        dst.copy_rect(&src, IRect::new(5, 5, 10, 10)).unwrap();
+        assert_eq!(&dst.samples()[..3], &[0xff, 0xff, 0xff]);
+        assert_eq!(dst.samples()[(9 * 10 + 9) * 3], 0);
+
+        dst.clear_with(0xff).unwrap();
+        dst.composite(&src, 0.5).unwrap();
+        assert_eq!(dst.samples()[0], 0xff);
+        let v = dst.samples()[(9 * 10 + 9) * 3];
+        assert!((126..=129).contains(&v));
+
+        // Unpremultiplied colors above their alpha saturate instead of wrapping
+        let mut translucent = Pixmap::new(&rgb, 0, 0, 1, 1, true).unwrap();
+        translucent
+            .samples_mut()
+            .copy_from_slice(&[0xff, 0xff, 0xff, 128]);
+        dst.clear_with(0xff).unwrap();
+        dst.composite(&translucent, 1.0).unwrap();
+        assert_eq!(&dst.samples()[..3], &[0xff, 0xff, 0xff]);
+
+        let gray = Pixmap::new_with_w_h(&Colorspace::device_gray(), 10, 10, false).unwrap();
+        assert!(dst.composite(&gray, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_pixmap_write_formats() {
+        let cs = Colorspace::device_rgb();
+        let mut pixmap = Pixmap::new_with_w_h(&cs, 20, 10, false).unwrap();
+        pixmap.clear_with(0x80).unwrap();
+
+        let mut options = ImageWriteOptions::default();
+        options.set_quality(75).set_resolution(300, 300);
+        let mut jpeg = Vec::new();
+        pixmap
+            .write_to_with_options(&mut jpeg, ImageFormat::JPEG, &options)
+            .unwrap();
+        assert!(jpeg.starts_with(&[0xff, 0xd8]));
+        assert_eq!(pixmap.resolution(), (96, 96));
+
+        let mut pbm = Vec::new();
+        pixmap
+            .write_to_with_options(&mut pbm, ImageFormat::PBM, &options)
+            .unwrap();
+        assert!(pbm.starts_with(b"P4"));
+
+        let mut pam = Vec::new();
+        pixmap.write_to(&mut pam, ImageFormat::PAM).unwrap();
+        assert!(pam.starts_with(b"P7"));
+
+        let uri = pixmap
+            .to_data_uri(ImageFormat::PNG, &ImageWriteOptions::default())
+            .unwrap();
+        assert!(uri.starts_with("data:image/png;base64,iVBORw0KGgo"));
+    }
+
+    #[test]
+    fn test_pixmap_from_samples() {
+        let gray = Colorspace::device_gray();
+        // 2x2 pixels with one byte of padding per row
+        let samples = [1, 2, 0, 3, 4, 0];
+        let pixmap = Pixmap::from_samples(&gray, 10, 20, 2, 2, false, 3, &samples).unwrap();
+        assert_eq!(pixmap.origin(), (10, 20));
+        assert_eq!(pixmap.stride(), 2);
+        assert_eq!(pixmap.samples(), &[1, 2, 3, 4]);
+
+        assert!(Pixmap::from_samples(&gray, 0, 0, 2, 2, false, 1, &samples).is_err());
+        assert!(Pixmap::from_samples(&gray, 0, 0, 2, 3, false, 3, &samples).is_err());
+    }
+
+    #[test]
+    fn test_pixmap_from_samples_mut() {
+        let rgb = Colorspace::device_rgb();
+        let mut samples = vec![0x80; 4 * 3 * 2];
+        let image = {
+            let pixmap =
+                Pixmap::from_samples_mut(&rgb, 0, 0, 4, 2, false, 12, &mut samples).unwrap();
+            assert_eq!(pixmap.samples().len(), 24);
+            Image::from_pixmap(&pixmap).unwrap()
+        };
+        samples.fill(0);
+        let copy = image.to_pixmap().unwrap();
+        assert!(copy.samples().iter().all(|&v| v == 0x80));
+    }
+
+    /// Deskewing, document detection and warping need MuPDF 1.24
+    const HAVE_DESKEW: bool =
+        FZ_VERSION_MAJOR > 1 || (FZ_VERSION_MAJOR == 1 && FZ_VERSION_MINOR >= 24);
+
+    /// A gray pixmap with a few horizontal "text lines"
+    fn lines_pixmap() -> Pixmap {
+        let gray = Colorspace::device_gray();
+        let mut pixmap = Pixmap::new_with_w_h(&gray, 200, 100, false).unwrap();
+        pixmap.clear_with(0xff).unwrap();
+        for row in [20, 40, 60, 80] {
+            let start = row * 200;
+            pixmap.samples_mut()[start + 20..start + 180].fill(0);
+        }
+        pixmap
+    }
+
+    #[test]
+    fn test_pixmap_deskew_and_warp() {
+        if !HAVE_DESKEW {
+            return;
+        }
+        let pixmap = lines_pixmap();
+        let skew = pixmap.detect_skew().unwrap();
+        assert!(skew.abs() < 1.0);
+        let deskewed = pixmap.deskew(5.0, DeskewBorder::Maintain).unwrap();
+        assert_eq!((deskewed.width(), deskewed.height()), (200, 100));
+
+        let quad = Quad::new(
+            Point::new(0.0, 0.0),
+            Point::new(100.0, 0.0),
+            Point::new(0.0, 50.0),
+            Point::new(100.0, 50.0),
+        );
+        let warped = pixmap.warp(&quad, 40, 20).unwrap();
+        assert_eq!((warped.width(), warped.height()), (40, 20));
+        let dewarped = pixmap.dewarp(&quad).unwrap();
+        assert_eq!((dewarped.width(), dewarped.height()), (100, 50));
+    }
+
+    #[test]
+    fn test_pixmap_detect_document() {
+        if !HAVE_DESKEW {
+            return;
+        }
+        // A white sheet on a dark background
+        let gray = Colorspace::device_gray();
+        let mut pixmap = Pixmap::new_with_w_h(&gray, 400, 300, false).unwrap();
+        pixmap.clear_with(0x20).unwrap();
+        for row in 60..240 {
+            let start = row * 400;
+            pixmap.samples_mut()[start + 80..start + 320].fill(0xff);
+        }
+
+        let quad = pixmap.detect_document().unwrap().expect("document found");
+        for corner in [&quad.ul, &quad.ur, &quad.ll, &quad.lr] {
+            assert!((0.0..=400.0).contains(&corner.x), "{:?}", quad);
+            assert!((0.0..=300.0).contains(&corner.y), "{:?}", quad);
+        }
+        assert!(quad.ul.x < quad.ur.x && quad.ul.y < quad.ll.y, "{:?}", quad);
+    }
+
+    #[test]
+    fn test_pixmap_deskew_unsupported() {
+        if HAVE_DESKEW {
+            return;
+        }
+        let pixmap = lines_pixmap();
+        let quad = Quad::new(
+            Point::new(0.0, 0.0),
+            Point::new(100.0, 0.0),
+            Point::new(0.0, 50.0),
+            Point::new(100.0, 50.0),
+        );
+        assert!(pixmap.detect_skew().is_err());
+        assert!(pixmap.deskew(5.0, DeskewBorder::Maintain).is_err());
+        assert!(pixmap.detect_document().is_err());
+        assert!(pixmap.warp(&quad, 40, 20).is_err());
+    }
+
+    #[test]
+    fn test_pixmap_analysis() {
+        let gray = Colorspace::device_gray();
+        let mut pixmap = Pixmap::new(&gray, 10, 10, 4, 4, false).unwrap();
+        pixmap.clear_with(0xff).unwrap();
+        assert_eq!(pixmap.ink_fraction(0), 0.0);
+        assert_eq!(pixmap.ink_bbox(0), None);
+        assert_eq!(pixmap.histogram()[0][0xff], 16);
+
+        pixmap.samples_mut()[4 + 1] = 0;
+        pixmap.samples_mut()[8 + 2] = 0xf8;
+        assert_eq!(pixmap.ink_fraction(0), 2.0 / 16.0);
+        assert_eq!(pixmap.ink_fraction(16), 1.0 / 16.0);
+        assert_eq!(pixmap.ink_bbox(0), Some(IRect::new(11, 11, 13, 13)));
+
+        let histogram = pixmap.histogram();
+        assert_eq!(histogram.len(), 1);
+        assert_eq!(histogram[0][0], 1);
+        assert_eq!(histogram[0][0xf8], 1);
+        assert_eq!(histogram[0][0xff], 14);
+
+        let mut cmyk = Pixmap::new_with_w_h(&Colorspace::device_cmyk(), 2, 2, false).unwrap();
+        cmyk.clear().unwrap();
+        assert_eq!(cmyk.ink_fraction(0), 0.0);
+    }
 }
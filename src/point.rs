@@ -1,9 +1,10 @@
 use mupdf_sys::{fz_point, fz_transform_point};
+use serde::{Deserialize, Serialize};
 
 use crate::Matrix;
 
 /// A point in a two-dimensional space.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -1,9 +1,10 @@
 use mupdf_sys::*;
+use serde::{Deserialize, Serialize};
 
 use crate::Point;
 
 /// A representation for a region defined by 4 points
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Quad {
     pub ul: Point,
     pub ur: Point,
@@ -27,3 +28,14 @@ impl From<fz_quad> for Quad {
         }
     }
 }
+
+impl From<Quad> for fz_quad {
+    fn from(quad: Quad) -> Self {
+        Self {
+            ul: quad.ul.into(),
+            ur: quad.ur.into(),
+            ll: quad.ll.into(),
+            lr: quad.lr.into(),
+        }
+    }
+}
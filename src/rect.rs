@@ -1,6 +1,7 @@
 use std::fmt;
 
 use mupdf_sys::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{context, Error, Matrix, Point, Quad, Size, StrokeState};
 
@@ -8,7 +9,7 @@ const FZ_MIN_INF_RECT: i32 = 0x80000000u32 as i32;
 const FZ_MAX_INF_RECT: i32 = 0x7fffff80u32 as i32;
 
 /// A rectangle using integers instead of floats
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct IRect {
     pub x0: i32,
     pub y0: i32,
@@ -101,7 +102,7 @@ impl From<IRect> for fz_irect {
 }
 
 /// A rectangle represented by two diagonally opposite corners at arbitrary coordinates
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct Rect {
     pub x0: f32,
     pub y0: f32,
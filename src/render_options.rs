@@ -0,0 +1,200 @@
+use crate::{
+    ColorParams, Colorspace, Cookie, DefaultColorspaces, IRect, Matrix, Rect, Separations,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Transform {
+    Matrix(Matrix),
+    Dpi(f32),
+    Fit(u32, u32),
+}
+
+/// Options for rendering a page to a pixmap
+///
+/// Defaults to rendering the whole page at 72 DPI into an opaque RGB pixmap
+/// with a white background, including annotations and widgets.
+#[derive(Debug, Clone)]
+pub struct RenderOptions<'a> {
+    transform: Transform,
+    colorspace: Option<&'a Colorspace>,
+    alpha: bool,
+    clip: IRect,
+    background: Option<[f32; 3]>,
+    annotations: bool,
+    widgets: bool,
+    aa_level: Option<i32>,
+    color_params: Option<ColorParams>,
+    separations: Option<&'a Separations>,
+    default_colorspaces: Option<&'a DefaultColorspaces>,
+    proof: Option<&'a Colorspace>,
+    cookie: Option<&'a Cookie>,
+}
+
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        Self {
+            transform: Transform::Matrix(Matrix::IDENTITY),
+            colorspace: None,
+            alpha: false,
+            clip: IRect::INF,
+            background: None,
+            annotations: true,
+            widgets: true,
+            aa_level: None,
+            color_params: None,
+            separations: None,
+            default_colorspaces: None,
+            proof: None,
+            cookie: None,
+        }
+    }
+}
+
+impl<'a> RenderOptions<'a> {
+    /// Render with an explicit transformation matrix
+    pub fn set_ctm(&mut self, ctm: Matrix) -> &mut Self {
+        self.transform = Transform::Matrix(ctm);
+        self
+    }
+
+    /// Render at the given resolution, 72 DPI is one pixel per point
+    pub fn set_dpi(&mut self, dpi: f32) -> &mut Self {
+        self.transform = Transform::Dpi(dpi);
+        self
+    }
+
+    /// Scale the page to fit into `width` x `height` pixels, keeping its aspect ratio
+    pub fn set_fit(&mut self, width: u32, height: u32) -> &mut Self {
+        self.transform = Transform::Fit(width, height);
+        self
+    }
+
+    /// The transformation matrix used for a page with the given bounds
+    pub fn ctm(&self, bounds: Rect) -> Matrix {
+        match self.transform {
+            Transform::Matrix(ref ctm) => ctm.clone(),
+            Transform::Dpi(dpi) => Matrix::new_scale(dpi / 72.0, dpi / 72.0),
+            Transform::Fit(width, height) => {
+                let sx = width as f32 / bounds.width();
+                let sy = height as f32 / bounds.height();
+                let scale = sx.min(sy);
+                Matrix::new_scale(scale, scale)
+            }
+        }
+    }
+
+    pub fn colorspace(&self) -> Option<&'a Colorspace> {
+        self.colorspace
+    }
+
+    pub fn set_colorspace(&mut self, cs: &'a Colorspace) -> &mut Self {
+        self.colorspace = Some(cs);
+        self
+    }
+
+    pub fn alpha(&self) -> bool {
+        self.alpha
+    }
+
+    pub fn set_alpha(&mut self, alpha: bool) -> &mut Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Area of the transformed page to render, in device pixels
+    pub fn clip(&self) -> IRect {
+        self.clip
+    }
+
+    pub fn set_clip(&mut self, clip: IRect) -> &mut Self {
+        self.clip = clip;
+        self
+    }
+
+    /// RGB background color, `None` is white or transparent when rendering with alpha
+    pub fn background(&self) -> Option<[f32; 3]> {
+        self.background
+    }
+
+    pub fn set_background(&mut self, rgb: [f32; 3]) -> &mut Self {
+        self.background = Some(rgb);
+        self
+    }
+
+    pub fn annotations(&self) -> bool {
+        self.annotations
+    }
+
+    pub fn set_annotations(&mut self, value: bool) -> &mut Self {
+        self.annotations = value;
+        self
+    }
+
+    pub fn widgets(&self) -> bool {
+        self.widgets
+    }
+
+    pub fn set_widgets(&mut self, value: bool) -> &mut Self {
+        self.widgets = value;
+        self
+    }
+
+    /// Number of anti-aliasing bits, `None` uses the level of the current context
+    pub fn aa_level(&self) -> Option<i32> {
+        self.aa_level
+    }
+
+    pub fn set_aa_level(&mut self, bits: i32) -> &mut Self {
+        self.aa_level = Some(bits);
+        self
+    }
+
+    /// Color parameters overriding the ones requested by the page content
+    pub fn color_params(&self) -> Option<ColorParams> {
+        self.color_params
+    }
+
+    pub fn set_color_params(&mut self, cp: ColorParams) -> &mut Self {
+        self.color_params = Some(cp);
+        self
+    }
+
+    pub fn separations(&self) -> Option<&'a Separations> {
+        self.separations
+    }
+
+    pub fn set_separations(&mut self, seps: &'a Separations) -> &mut Self {
+        self.separations = Some(seps);
+        self
+    }
+
+    /// Colorspaces used instead of the ones the page itself asks for
+    pub fn default_colorspaces(&self) -> Option<&'a DefaultColorspaces> {
+        self.default_colorspaces
+    }
+
+    pub fn set_default_colorspaces(&mut self, default_cs: &'a DefaultColorspaces) -> &mut Self {
+        self.default_colorspaces = Some(default_cs);
+        self
+    }
+
+    /// Profile to soft-proof against, such as a press profile or
+    /// [`Document::output_intent`](crate::Document::output_intent)
+    pub fn proof(&self) -> Option<&'a Colorspace> {
+        self.proof
+    }
+
+    pub fn set_proof(&mut self, proof: &'a Colorspace) -> &mut Self {
+        self.proof = Some(proof);
+        self
+    }
+
+    pub fn cookie(&self) -> Option<&'a Cookie> {
+        self.cookie
+    }
+
+    pub fn set_cookie(&mut self, cookie: &'a Cookie) -> &mut Self {
+        self.cookie = Some(cookie);
+        self
+    }
+}
@@ -1,6 +1,22 @@
+use std::convert::TryInto;
+use std::ffi::CStr;
+
 use mupdf_sys::*;
+use num_enum::TryFromPrimitive;
+
+use crate::{context, Colorspace, Error, Pixmap};
 
-use crate::context;
+/// How a separation is rendered
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
+#[repr(u32)]
+pub enum SeparationBehavior {
+    /// Mixed into the process colors using its equivalent color
+    Composite = fz_separation_behavior_FZ_SEPARATION_COMPOSITE as u32,
+    /// Rendered into a channel of its own
+    Spot = fz_separation_behavior_FZ_SEPARATION_SPOT as u32,
+    /// Not rendered at all
+    Disabled = fz_separation_behavior_FZ_SEPARATION_DISABLED as u32,
+}
 
 #[derive(Debug)]
 pub struct Separations {
@@ -23,6 +39,81 @@ impl Separations {
     pub fn active_count(&self) -> usize {
         unsafe { fz_count_active_separations(context(), self.inner) as usize }
     }
+
+    fn check_index(&self, index: usize) -> Result<(), Error> {
+        if index >= self.len() {
+            return Err(Error::InvalidArgument(format!(
+                "separation index {} out of range for {} separations",
+                index,
+                self.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Name of the separation at `index`, `None` if `index` is out of range
+    pub fn name(&self, index: usize) -> Option<String> {
+        if index >= self.len() {
+            return None;
+        }
+        unsafe {
+            let ptr = fz_separation_name(context(), self.inner, index as _);
+            if ptr.is_null() {
+                return Some(String::new());
+            }
+            Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+        }
+    }
+
+    pub fn behavior(&self, index: usize) -> Option<SeparationBehavior> {
+        if index >= self.len() {
+            return None;
+        }
+        unsafe {
+            let behavior = fz_separation_current_behavior(context(), self.inner, index as _);
+            (behavior as u32).try_into().ok()
+        }
+    }
+
+    pub fn set_behavior(
+        &mut self,
+        index: usize,
+        behavior: SeparationBehavior,
+    ) -> Result<(), Error> {
+        self.check_index(index)?;
+        unsafe {
+            fz_set_separation_behavior(context(), self.inner, index as _, behavior as _);
+        }
+        Ok(())
+    }
+
+    /// Color to use for the separation at `index` when it is not rendered as a spot
+    pub fn equivalent_color(&self, index: usize, cs: &Colorspace) -> Result<Vec<f32>, Error> {
+        self.check_index(index)?;
+        // fz_separation_equivalent writes up to FZ_MAX_COLORS components
+        let mut color = vec![0.0; FZ_MAX_COLORS as usize];
+        unsafe {
+            ffi_try!(mupdf_separation_equivalent(
+                context(),
+                self.inner,
+                index as _,
+                cs.inner,
+                color.as_mut_ptr()
+            ));
+        }
+        color.truncate(cs.n() as usize);
+        Ok(color)
+    }
+
+    pub fn equivalent_rgb(&self, index: usize) -> Result<[f32; 3], Error> {
+        let rgb = self.equivalent_color(index, &Colorspace::device_rgb())?;
+        Ok([rgb[0], rgb[1], rgb[2]])
+    }
+
+    pub fn equivalent_cmyk(&self, index: usize) -> Result<[f32; 4], Error> {
+        let cmyk = self.equivalent_color(index, &Colorspace::device_cmyk())?;
+        Ok([cmyk[0], cmyk[1], cmyk[2], cmyk[3]])
+    }
 }
 
 impl Drop for Separations {
@@ -32,3 +123,48 @@ impl Drop for Separations {
         }
     }
 }
+
+/// A single printing plate, see [`Page::to_plates`](crate::Page::to_plates)
+#[derive(Debug)]
+pub struct Plate {
+    pub name: String,
+    /// Gray pixmap where black means full ink coverage
+    pub pixmap: Pixmap,
+}
+
+/// Split a CMYK pixmap with spot channels into one gray pixmap per channel
+pub(crate) fn split_plates(pixmap: &Pixmap, seps: &Separations) -> Result<Vec<Plate>, Error> {
+    let mut names: Vec<String> = ["Cyan", "Magenta", "Yellow", "Black"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    for i in 0..seps.len() {
+        if seps.behavior(i) == Some(SeparationBehavior::Spot) {
+            names.push(seps.name(i).unwrap_or_default());
+        }
+    }
+
+    let gray = Colorspace::device_gray();
+    let n = pixmap.n() as usize;
+    let width = pixmap.width() as usize;
+    let stride = pixmap.stride() as usize;
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(channel, name)| {
+            let mut plate = Pixmap::new_with_rect(&gray, pixmap.rect(), false)?;
+            let plate_stride = plate.stride() as usize;
+            let dst = plate.samples_mut();
+            for (y, row) in pixmap.samples().chunks(stride).enumerate() {
+                let dst = &mut dst[y * plate_stride..y * plate_stride + width];
+                for (x, value) in dst.iter_mut().enumerate() {
+                    *value = 255 - row[x * n + channel];
+                }
+            }
+            Ok(Plate {
+                name,
+                pixmap: plate,
+            })
+        })
+        .collect()
+}
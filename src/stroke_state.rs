@@ -2,10 +2,11 @@ use std::convert::TryFrom;
 
 use mupdf_sys::*;
 use num_enum::TryFromPrimitive;
+use serde::{Deserialize, Serialize};
 
 use crate::{context, Error};
 
-#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive, Serialize, Deserialize)]
 #[repr(u32)]
 pub enum LineCap {
     Butt = 0,
@@ -20,7 +21,7 @@ impl Default for LineCap {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive, Serialize, Deserialize)]
 #[repr(u32)]
 pub enum LineJoin {
     Miter = 0,
@@ -41,6 +42,10 @@ pub struct StrokeState {
 }
 
 impl StrokeState {
+    pub(crate) unsafe fn from_raw(ptr: *mut fz_stroke_state) -> Self {
+        Self { inner: ptr }
+    }
+
     pub fn new(
         start_cap: LineCap,
         dash_cap: LineCap,
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+
+use crate::text_page::{TextBlock, TextBlockType, TextLine};
+use crate::{Matrix, Point, Quad, Rect, TextCharFlags, TextPage, WriteMode};
+
+/// Owned copy of a [`TextPage`] that can be kept, compared and (de)serialized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructuredText {
+    pub bounds: Rect,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Block {
+    Text {
+        bbox: Rect,
+        lines: Vec<Line>,
+    },
+    Image {
+        bbox: Rect,
+        transform: Matrix,
+        /// Size of the image in pixels
+        width: u32,
+        height: u32,
+    },
+}
+
+impl Block {
+    pub fn bbox(&self) -> Rect {
+        match self {
+            Block::Text { bbox, .. } | Block::Image { bbox, .. } => *bbox,
+        }
+    }
+
+    /// Lines of a text block, empty for images
+    pub fn lines(&self) -> &[Line] {
+        match self {
+            Block::Text { lines, .. } => lines,
+            Block::Image { .. } => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Line {
+    pub bbox: Rect,
+    pub wmode: WriteMode,
+    pub dir: Point,
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+/// Consecutive chars of a line in the same style, see [`crate::TextRun`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub bbox: Rect,
+    pub font: FontInfo,
+    pub size: f32,
+    /// Fill color in sRGB as `0xAARRGGBB`
+    pub color: u32,
+    pub bidi_level: u32,
+    pub text: String,
+    pub chars: Vec<Char>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FontInfo {
+    pub name: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub monospaced: bool,
+    pub serif: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Char {
+    /// The character, `U+FFFD` if MuPDF could not map the glyph to unicode
+    pub c: char,
+    pub origin: Point,
+    pub quad: Quad,
+    /// Bits of [`TextCharFlags`]
+    pub flags: u32,
+}
+
+impl Char {
+    pub fn flags(&self) -> TextCharFlags {
+        TextCharFlags::from_bits_truncate(self.flags)
+    }
+}
+
+impl TextPage {
+    /// Copy all blocks, lines and chars of this page into an owned [`StructuredText`]
+    pub fn to_structured_text(&self) -> StructuredText {
+        StructuredText {
+            bounds: self.bounds(),
+            blocks: self.blocks().map(|block| convert_block(&block)).collect(),
+        }
+    }
+}
+
+fn convert_block(block: &TextBlock) -> Block {
+    let bbox = block.bounds();
+    match block.r#type() {
+        TextBlockType::Text => Block::Text {
+            bbox,
+            lines: block.lines().map(|line| convert_line(&line)).collect(),
+        },
+        TextBlockType::Image => {
+            let (width, height) = block
+                .image()
+                .map_or((0, 0), |image| (image.width(), image.height()));
+            Block::Image {
+                bbox,
+                transform: block.ctm().unwrap_or(Matrix::IDENTITY),
+                width,
+                height,
+            }
+        }
+    }
+}
+
+fn convert_line(line: &TextLine) -> Line {
+    let spans = line
+        .runs()
+        .map(|run| {
+            let font = run.font();
+            let chars: Vec<Char> = run
+                .chars()
+                .map(|ch| Char {
+                    c: ch.char().unwrap_or(char::REPLACEMENT_CHARACTER),
+                    origin: ch.origin(),
+                    quad: ch.quad(),
+                    flags: ch.flags().bits(),
+                })
+                .collect();
+            Span {
+                bbox: run.bounds(),
+                font: FontInfo {
+                    name: font.name().to_owned(),
+                    bold: font.is_bold(),
+                    italic: font.is_italic(),
+                    monospaced: font.is_monospaced(),
+                    serif: font.is_serif(),
+                },
+                size: run.size(),
+                color: run.color(),
+                bidi_level: run.bidi_level(),
+                text: chars.iter().map(|ch| ch.c).collect(),
+                chars,
+            }
+        })
+        .collect();
+    Line {
+        bbox: line.bounds(),
+        wmode: line.wmode(),
+        dir: line.dir(),
+        spans,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Block, StructuredText};
+    use crate::{Document, TextPageOptions};
+
+    #[test]
+    fn test_structured_text() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let text_page = page0.to_text_page(TextPageOptions::empty()).unwrap();
+        let stext = text_page.to_structured_text();
+        assert_eq!(stext.bounds, page0.bounds().unwrap());
+
+        let line = stext
+            .blocks
+            .iter()
+            .flat_map(|block| block.lines())
+            .next()
+            .unwrap();
+        assert_eq!(line.text(), "Dummy PDF file");
+        let first = &line.spans[0].chars[0];
+        assert_eq!(first.c, 'D');
+        // Fractional positions are kept
+        assert_eq!(first.quad.ul.x, 56.8);
+        assert!(matches!(stext.blocks[0], Block::Text { .. }));
+
+        let json = serde_json::to_string(&stext).unwrap();
+        assert!(json.contains(r#""type":"text""#));
+        let parsed: StructuredText = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, stext);
+    }
+}
@@ -0,0 +1,804 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Drawing, DrawingKind, DrawingSegment, Error, Page, Point, Rect, TextPage, TextPageOptions,
+};
+
+/// Options for finding tables
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableOptions {
+    tolerance: f32,
+    min_rows: usize,
+    min_columns: usize,
+    text_tables: bool,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 3.0,
+            min_rows: 2,
+            min_columns: 2,
+            text_tables: false,
+        }
+    }
+}
+
+impl TableOptions {
+    /// Distance in points below which ruling lines are considered aligned or touching
+    pub fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: f32) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn min_rows(&self) -> usize {
+        self.min_rows
+    }
+
+    pub fn set_min_rows(&mut self, rows: usize) -> &mut Self {
+        self.min_rows = rows;
+        self
+    }
+
+    pub fn min_columns(&self) -> usize {
+        self.min_columns
+    }
+
+    pub fn set_min_columns(&mut self, columns: usize) -> &mut Self {
+        self.min_columns = columns;
+        self
+    }
+
+    /// Also look for tables without ruling lines by aligning text into columns
+    ///
+    /// Off by default, multi-column layouts and forms line up just like table columns
+    /// and would be reported as tables.
+    pub fn text_tables(&self) -> bool {
+        self.text_tables
+    }
+
+    pub fn set_text_tables(&mut self, enabled: bool) -> &mut Self {
+        self.text_tables = enabled;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableCell {
+    pub bbox: Rect,
+    /// Text inside the cell, lines separated by `\n`
+    pub text: String,
+}
+
+/// A table found on a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub bbox: Rect,
+    /// Cells in row-major order, every row has the same number of columns
+    pub rows: Vec<Vec<TableCell>>,
+    /// Whether the table was found from ruling lines rather than text alignment
+    pub ruled: bool,
+}
+
+impl Table {
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    pub fn cell(&self, row: usize, column: usize) -> Option<&TableCell> {
+        self.rows.get(row)?.get(column)
+    }
+}
+
+/// A horizontal or vertical line segment, `pos` is the y or x coordinate it lies on
+#[derive(Debug, Clone, Copy)]
+struct Ruling {
+    pos: f32,
+    start: f32,
+    end: f32,
+}
+
+/// Bounds of the points of each subpath, control points included
+fn subpath_bounds(segments: &[DrawingSegment]) -> Vec<Rect> {
+    let mut bounds: Vec<Rect> = Vec::new();
+    let mut extend = |p: Point, start: bool| match bounds.last_mut() {
+        Some(rect) if !start => {
+            rect.x0 = rect.x0.min(p.x);
+            rect.y0 = rect.y0.min(p.y);
+            rect.x1 = rect.x1.max(p.x);
+            rect.y1 = rect.y1.max(p.y);
+        }
+        _ => bounds.push(Rect::new(p.x, p.y, p.x, p.y)),
+    };
+    for segment in segments {
+        match *segment {
+            DrawingSegment::MoveTo(p) => extend(p, true),
+            DrawingSegment::LineTo(p) => extend(p, false),
+            DrawingSegment::CurveTo(a, b, p) => {
+                extend(a, false);
+                extend(b, false);
+                extend(p, false);
+            }
+            DrawingSegment::Close => {}
+        }
+    }
+    bounds
+}
+
+/// Axis-aligned edges of stroked paths and thin filled subpaths, e.g. one `re` per rule
+fn collect_rulings(drawings: &[Drawing], tolerance: f32) -> (Vec<Ruling>, Vec<Ruling>) {
+    let mut horizontal = Vec::new();
+    let mut vertical = Vec::new();
+    for drawing in drawings {
+        if drawing.kind == DrawingKind::Fill {
+            for bounds in subpath_bounds(&drawing.segments) {
+                if bounds.height() <= tolerance && bounds.width() > tolerance {
+                    horizontal.push(Ruling {
+                        pos: (bounds.y0 + bounds.y1) / 2.0,
+                        start: bounds.x0,
+                        end: bounds.x1,
+                    });
+                } else if bounds.width() <= tolerance && bounds.height() > tolerance {
+                    vertical.push(Ruling {
+                        pos: (bounds.x0 + bounds.x1) / 2.0,
+                        start: bounds.y0,
+                        end: bounds.y1,
+                    });
+                }
+            }
+            continue;
+        }
+
+        let mut add_edge = |a: Point, b: Point| {
+            let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+            if dy <= tolerance && dx > tolerance {
+                horizontal.push(Ruling {
+                    pos: (a.y + b.y) / 2.0,
+                    start: a.x.min(b.x),
+                    end: a.x.max(b.x),
+                });
+            } else if dx <= tolerance && dy > tolerance {
+                vertical.push(Ruling {
+                    pos: (a.x + b.x) / 2.0,
+                    start: a.y.min(b.y),
+                    end: a.y.max(b.y),
+                });
+            }
+        };
+        let (mut start, mut current) = (None, None);
+        for segment in &drawing.segments {
+            match *segment {
+                DrawingSegment::MoveTo(p) => {
+                    start = Some(p);
+                    current = Some(p);
+                }
+                DrawingSegment::LineTo(p) => {
+                    if let Some(c) = current {
+                        add_edge(c, p);
+                    }
+                    current = Some(p);
+                }
+                DrawingSegment::CurveTo(_, _, p) => current = Some(p),
+                DrawingSegment::Close => {
+                    if let (Some(c), Some(s)) = (current, start) {
+                        add_edge(c, s);
+                    }
+                    current = start;
+                }
+            }
+        }
+    }
+    (
+        merge_rulings(horizontal, tolerance),
+        merge_rulings(vertical, tolerance),
+    )
+}
+
+/// Join rulings on the same line that overlap or nearly touch
+fn merge_rulings(mut rulings: Vec<Ruling>, tolerance: f32) -> Vec<Ruling> {
+    rulings.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    let mut merged = Vec::new();
+    let mut index = 0;
+    while index < rulings.len() {
+        let first = rulings[index].pos;
+        let mut end = index;
+        while end < rulings.len() && rulings[end].pos - first <= tolerance {
+            end += 1;
+        }
+        let line = &mut rulings[index..end];
+        let pos = line.iter().map(|r| r.pos).sum::<f32>() / line.len() as f32;
+        line.sort_by(|a, b| a.start.total_cmp(&b.start));
+        let mut current = Ruling { pos, ..line[0] };
+        for ruling in &line[1..] {
+            if ruling.start <= current.end + tolerance {
+                current.end = current.end.max(ruling.end);
+            } else {
+                merged.push(current);
+                current = Ruling { pos, ..*ruling };
+            }
+        }
+        merged.push(current);
+        index = end;
+    }
+    merged
+}
+
+fn crosses(h: &Ruling, v: &Ruling, tolerance: f32) -> bool {
+    v.pos >= h.start - tolerance
+        && v.pos <= h.end + tolerance
+        && h.pos >= v.start - tolerance
+        && h.pos <= v.end + tolerance
+}
+
+/// Sorted positions with neighbors closer than `tolerance` collapsed
+fn distinct(mut positions: Vec<f32>, tolerance: f32) -> Vec<f32> {
+    positions.sort_by(f32::total_cmp);
+    let mut result: Vec<f32> = Vec::new();
+    for pos in positions {
+        match result.last() {
+            Some(last) if pos - last <= tolerance => {}
+            _ => result.push(pos),
+        }
+    }
+    result
+}
+
+/// Groups of horizontal and vertical rulings connected by intersections, as grid lines
+fn ruled_grids(
+    horizontal: &[Ruling],
+    vertical: &[Ruling],
+    options: &TableOptions,
+) -> Vec<(Vec<f32>, Vec<f32>)> {
+    let tolerance = options.tolerance;
+    // Union-find over horizontal rulings followed by vertical rulings
+    let mut parent: Vec<usize> = (0..horizontal.len() + vertical.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for (hi, h) in horizontal.iter().enumerate() {
+        for (vi, v) in vertical.iter().enumerate() {
+            if crosses(h, v, tolerance) {
+                let (a, b) = (
+                    find(&mut parent, hi),
+                    find(&mut parent, horizontal.len() + vi),
+                );
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut grids = Vec::new();
+    let mut done = vec![false; parent.len()];
+    for root in 0..parent.len() {
+        let root = find(&mut parent, root);
+        if done[root] {
+            continue;
+        }
+        done[root] = true;
+        let (mut ys, mut xs) = (Vec::new(), Vec::new());
+        for index in 0..parent.len() {
+            if find(&mut parent, index) != root {
+                continue;
+            }
+            match index.checked_sub(horizontal.len()) {
+                None => ys.push(horizontal[index].pos),
+                Some(vi) => xs.push(vertical[vi].pos),
+            }
+        }
+        let (xs, ys) = (distinct(xs, tolerance), distinct(ys, tolerance));
+        if xs.len() > options.min_columns.max(1) && ys.len() > options.min_rows.max(1) {
+            grids.push((xs, ys));
+        }
+    }
+    grids.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]).then(a.0[0].total_cmp(&b.0[0])));
+    grids
+}
+
+/// Horizontal rulings crossing no vertical ruling, grouped by their common extent
+///
+/// Returns the extent and the sorted positions of each group of at least two rulings.
+fn horizontal_stacks(
+    horizontal: &[Ruling],
+    vertical: &[Ruling],
+    tolerance: f32,
+) -> Vec<((f32, f32), Vec<f32>)> {
+    let mut stacks: Vec<((f32, f32), Vec<f32>)> = Vec::new();
+    for h in horizontal {
+        if vertical.iter().any(|v| crosses(h, v, tolerance)) {
+            continue;
+        }
+        let stack = stacks.iter_mut().find(|((start, end), _)| {
+            (h.start - start).abs() <= tolerance && (h.end - end).abs() <= tolerance
+        });
+        match stack {
+            Some((_, ys)) => ys.push(h.pos),
+            None => stacks.push(((h.start, h.end), vec![h.pos])),
+        }
+    }
+    stacks
+        .into_iter()
+        .map(|(extent, ys)| (extent, distinct(ys, tolerance)))
+        .filter(|(_, ys)| ys.len() > 1)
+        .collect()
+}
+
+/// Table between horizontal rulings at `ys`, with columns from the text between them
+fn horizontal_table(
+    (x0, x1): (f32, f32),
+    ys: &[f32],
+    glyphs: &[Glyph],
+    options: &TableOptions,
+) -> Option<Table> {
+    if ys.len() <= options.min_rows.max(1) {
+        return None;
+    }
+    let area = Rect::new(x0, ys[0], x1, ys[ys.len() - 1]);
+    let inner: Vec<&Glyph> = glyphs
+        .iter()
+        .filter(|g| inside(&area, g.center()))
+        .collect();
+    let fragments = fragments(inner);
+    let columns = column_spans(fragments.iter(), options.tolerance);
+    if columns.len() < options.min_columns.max(1) {
+        return None;
+    }
+    let mut xs = vec![x0];
+    xs.extend(columns.windows(2).map(|c| (c[0].1 + c[1].0) / 2.0));
+    xs.push(x1);
+    Some(grid_table(&xs, ys, glyphs, true))
+}
+
+/// A character of the page with the index of the line it belongs to
+struct Glyph {
+    c: char,
+    bbox: Rect,
+    size: f32,
+    line: usize,
+}
+
+impl Glyph {
+    fn center(&self) -> Point {
+        Point::new(
+            (self.bbox.x0 + self.bbox.x1) / 2.0,
+            (self.bbox.y0 + self.bbox.y1) / 2.0,
+        )
+    }
+}
+
+fn collect_glyphs(text_page: &TextPage) -> Vec<Glyph> {
+    let mut glyphs = Vec::new();
+    let lines = text_page
+        .blocks()
+        .flat_map(|block| block.lines().collect::<Vec<_>>());
+    for (index, line) in lines.enumerate() {
+        for ch in line.chars() {
+            glyphs.push(Glyph {
+                c: ch.char().unwrap_or(char::REPLACEMENT_CHARACTER),
+                bbox: ch.quad().into(),
+                size: ch.size(),
+                line: index,
+            });
+        }
+    }
+    glyphs
+}
+
+fn inside(rect: &Rect, p: Point) -> bool {
+    p.x >= rect.x0 && p.x < rect.x1 && p.y >= rect.y0 && p.y < rect.y1
+}
+
+/// Text of the glyphs whose center lies in `rect`, one line of text per page line
+fn text_in(glyphs: &[Glyph], rect: &Rect) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut last_line = None;
+    for glyph in glyphs.iter().filter(|g| inside(rect, g.center())) {
+        if last_line != Some(glyph.line) {
+            lines.push(String::new());
+            last_line = Some(glyph.line);
+        }
+        lines.last_mut().unwrap().push(glyph.c);
+    }
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn grid_table(xs: &[f32], ys: &[f32], glyphs: &[Glyph], ruled: bool) -> Table {
+    let rows = ys
+        .windows(2)
+        .map(|y| {
+            xs.windows(2)
+                .map(|x| {
+                    let bbox = Rect::new(x[0], y[0], x[1], y[1]);
+                    TableCell {
+                        text: text_in(glyphs, &bbox),
+                        bbox,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Table {
+        bbox: Rect::new(xs[0], ys[0], xs[xs.len() - 1], ys[ys.len() - 1]),
+        rows,
+        ruled,
+    }
+}
+
+/// Run of glyphs on one line without a column-sized gap
+struct Fragment {
+    bbox: Rect,
+    text: String,
+}
+
+/// Split page lines into fragments at gaps wider than the font size
+fn fragments<'a>(glyphs: impl IntoIterator<Item = &'a Glyph>) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+    let mut current: Option<(Fragment, usize)> = None;
+    for glyph in glyphs {
+        if glyph.c.is_whitespace() {
+            continue;
+        }
+        let split = match &current {
+            Some((fragment, line)) => {
+                *line != glyph.line || glyph.bbox.x0 - fragment.bbox.x1 > glyph.size
+            }
+            None => true,
+        };
+        if split {
+            fragments.extend(current.take().map(|(fragment, _)| fragment));
+            current = Some((
+                Fragment {
+                    bbox: glyph.bbox,
+                    text: String::new(),
+                },
+                glyph.line,
+            ));
+        }
+        let (fragment, _) = current.as_mut().unwrap();
+        if glyph.bbox.x0 - fragment.bbox.x1 > glyph.size / 8.0 && !fragment.text.is_empty() {
+            fragment.text.push(' ');
+        }
+        fragment.text.push(glyph.c);
+        fragment.bbox.union(glyph.bbox);
+    }
+    fragments.extend(current.map(|(fragment, _)| fragment));
+    fragments
+}
+
+/// Find tables without rulings from fragments lining up in columns over consecutive rows
+fn text_tables(glyphs: &[Glyph], options: &TableOptions) -> Vec<Table> {
+    let mut fragments = fragments(glyphs);
+    fragments.sort_by(|a, b| (a.bbox.y0 + a.bbox.y1).total_cmp(&(b.bbox.y0 + b.bbox.y1)));
+
+    // Rows of vertically overlapping fragments, each sorted left to right
+    let mut rows: Vec<(Rect, Vec<Fragment>)> = Vec::new();
+    for fragment in fragments {
+        let center = (fragment.bbox.y0 + fragment.bbox.y1) / 2.0;
+        match rows.last_mut() {
+            Some((bbox, row)) if center >= bbox.y0 && center <= bbox.y1 => {
+                bbox.union(fragment.bbox);
+                row.push(fragment);
+            }
+            _ => rows.push((fragment.bbox, vec![fragment])),
+        }
+    }
+    for (_, row) in rows.iter_mut() {
+        row.sort_by(|a, b| a.bbox.x0.total_cmp(&b.bbox.x0));
+    }
+
+    let mut tables = Vec::new();
+    let mut start = 0;
+    while start < rows.len() {
+        // Grow a run of rows that keep at least `min_columns` separate columns
+        let mut end = start;
+        let mut columns = Vec::new();
+        while end < rows.len() && rows[end].1.len() >= options.min_columns {
+            if end > start {
+                let (prev, next) = (rows[end - 1].0, rows[end].0);
+                if next.y0 - prev.y1 > 1.5 * prev.height().max(next.height()) {
+                    break;
+                }
+            }
+            let spans = column_spans(
+                rows[start..=end].iter().flat_map(|(_, row)| row),
+                options.tolerance,
+            );
+            if spans.len() < options.min_columns {
+                break;
+            }
+            columns = spans;
+            end += 1;
+        }
+        if end > start && end - start >= options.min_rows {
+            tables.push(text_table(&rows[start..end], &columns));
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+    tables
+}
+
+/// Horizontal extents covered by `fragments`, gaps between them separate columns
+fn column_spans<'a>(
+    fragments: impl Iterator<Item = &'a Fragment>,
+    tolerance: f32,
+) -> Vec<(f32, f32)> {
+    let mut spans: Vec<(f32, f32)> = fragments.map(|f| (f.bbox.x0, f.bbox.x1)).collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f32, f32)> = Vec::new();
+    for (x0, x1) in spans {
+        match merged.last_mut() {
+            Some(last) if x0 <= last.1 + tolerance => last.1 = last.1.max(x1),
+            _ => merged.push((x0, x1)),
+        }
+    }
+    merged
+}
+
+fn text_table(rows: &[(Rect, Vec<Fragment>)], columns: &[(f32, f32)]) -> Table {
+    let mut xs = vec![columns[0].0];
+    xs.extend(columns.windows(2).map(|c| (c[0].1 + c[1].0) / 2.0));
+    xs.push(columns[columns.len() - 1].1);
+    let mut ys = vec![rows[0].0.y0];
+    ys.extend(rows.windows(2).map(|r| (r[0].0.y1 + r[1].0.y0) / 2.0));
+    ys.push(rows[rows.len() - 1].0.y1);
+
+    let cells = rows
+        .iter()
+        .zip(ys.windows(2))
+        .map(|((_, row), y)| {
+            xs.windows(2)
+                .map(|x| {
+                    let bbox = Rect::new(x[0], y[0], x[1], y[1]);
+                    let text = row
+                        .iter()
+                        .filter(|f| {
+                            let center = (f.bbox.x0 + f.bbox.x1) / 2.0;
+                            center >= x[0] && center <= x[1]
+                        })
+                        .map(|f| f.text.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    TableCell { bbox, text }
+                })
+                .collect()
+        })
+        .collect();
+    Table {
+        bbox: Rect::new(xs[0], ys[0], xs[xs.len() - 1], ys[ys.len() - 1]),
+        rows: cells,
+        ruled: false,
+    }
+}
+
+impl TextPage {
+    /// Find tables from the ruling lines in `drawings` and, if enabled in `options`,
+    /// from columns of aligned text
+    ///
+    /// Rulings crossing each other form the grid of a table. Stacks of horizontal rulings
+    /// of the same width without vertical ones, as on statements and invoices, make a
+    /// table with one row between each pair of rulings and columns taken from the
+    /// alignment of the text between them.
+    ///
+    /// Pass the result of [`Page::drawings`] of the same page, or an empty slice with
+    /// [`TableOptions::set_text_tables`] enabled to only use text alignment.
+    pub fn find_tables(&self, drawings: &[Drawing], options: &TableOptions) -> Vec<Table> {
+        let mut glyphs = collect_glyphs(self);
+        let (horizontal, vertical) = collect_rulings(drawings, options.tolerance);
+        let mut tables: Vec<Table> = ruled_grids(&horizontal, &vertical, options)
+            .iter()
+            .map(|(xs, ys)| grid_table(xs, ys, &glyphs, true))
+            .collect();
+        tables.extend(
+            horizontal_stacks(&horizontal, &vertical, options.tolerance)
+                .iter()
+                .filter_map(|(extent, ys)| horizontal_table(*extent, ys, &glyphs, options)),
+        );
+        if options.text_tables {
+            glyphs.retain(|g| !tables.iter().any(|t| inside(&t.bbox, g.center())));
+            tables.extend(text_tables(&glyphs, options));
+        }
+        tables
+    }
+}
+
+impl Page {
+    /// Find tables using the text and vector content of this page,
+    /// see [`TextPage::find_tables`]
+    pub fn find_tables(&self, options: &TableOptions) -> Result<Vec<Table>, Error> {
+        let text_page = self.to_text_page(TextPageOptions::empty())?;
+        let drawings = self.drawings()?;
+        Ok(text_page.find_tables(&drawings, options))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TableOptions;
+    use crate::page::test::pdf_with_content;
+    use crate::{Document, Drawing, DrawingKind, DrawingSegment, Point, Rect, TextPageOptions};
+
+    /// Three rows of three words in columns at x = 20, 120 and 220
+    const ROWS: &str = "BT /F1 10 Tf \
+        1 0 0 1 20 250 Tm (Date) Tj 1 0 0 1 120 250 Tm (Item) Tj 1 0 0 1 220 250 Tm (Total) Tj \
+        1 0 0 1 20 230 Tm (Mon) Tj 1 0 0 1 120 230 Tm (Tea) Tj 1 0 0 1 220 230 Tm (3) Tj \
+        1 0 0 1 20 210 Tm (Tue) Tj 1 0 0 1 120 210 Tm (Cake) Tj 1 0 0 1 220 210 Tm (5) Tj ET";
+
+    fn line(x0: f32, y0: f32, x1: f32, y1: f32) -> Drawing {
+        Drawing {
+            kind: DrawingKind::Stroke,
+            segments: vec![
+                DrawingSegment::MoveTo(Point::new(x0, y0)),
+                DrawingSegment::LineTo(Point::new(x1, y1)),
+            ],
+            fill_color: None,
+            stroke_color: Some([0.0; 3]),
+            stroke: None,
+            opacity: 1.0,
+            even_odd: false,
+            bounds: Rect::new(x0, y0, x1, y1),
+        }
+    }
+
+    #[test]
+    fn test_find_ruled_table() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let text_page = page0.to_text_page(TextPageOptions::empty()).unwrap();
+
+        // A 2x2 grid splitting "Dummy PDF file" after the first word
+        let mut drawings = Vec::new();
+        for y in [60.0, 95.0, 130.0] {
+            drawings.push(line(50.0, y, 250.0, y));
+        }
+        for x in [50.0, 116.5, 250.0] {
+            drawings.push(line(x, 60.0, x, 130.0));
+        }
+        let tables = text_page.find_tables(&drawings, &TableOptions::default());
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert!(table.ruled);
+        assert_eq!((table.row_count(), table.column_count()), (2, 2));
+        assert_eq!(table.bbox, Rect::new(50.0, 60.0, 250.0, 130.0));
+        assert_eq!(table.cell(0, 0).unwrap().text, "Dummy");
+        assert_eq!(table.cell(0, 1).unwrap().text, "PDF file");
+        assert!(table.cell(1, 0).unwrap().text.is_empty());
+
+        // Without rulings the single line of text is no table
+        assert!(!TableOptions::default().text_tables());
+        assert!(text_page
+            .find_tables(&[], &TableOptions::default())
+            .is_empty());
+        let mut options = TableOptions::default();
+        options.set_text_tables(true);
+        assert!(text_page.find_tables(&[], &options).is_empty());
+        assert!(page0
+            .find_tables(&TableOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_find_filled_grid_table() {
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let text_page = page0.to_text_page(TextPageOptions::empty()).unwrap();
+
+        // The grid of the ruled test as thin rectangles filled in a single path
+        let mut rects = Vec::new();
+        for y in [60.0, 95.0, 130.0] {
+            rects.push(Rect::new(50.0, y - 0.5, 250.0, y + 0.5));
+        }
+        for x in [50.0, 116.5, 250.0] {
+            rects.push(Rect::new(x - 0.5, 60.0, x + 0.5, 130.0));
+        }
+        let mut segments = Vec::new();
+        for r in &rects {
+            segments.extend([
+                DrawingSegment::MoveTo(Point::new(r.x0, r.y0)),
+                DrawingSegment::LineTo(Point::new(r.x1, r.y0)),
+                DrawingSegment::LineTo(Point::new(r.x1, r.y1)),
+                DrawingSegment::LineTo(Point::new(r.x0, r.y1)),
+                DrawingSegment::Close,
+            ]);
+        }
+        let grid = Drawing {
+            kind: DrawingKind::Fill,
+            segments,
+            fill_color: Some([0.0; 3]),
+            stroke_color: None,
+            stroke: None,
+            opacity: 1.0,
+            even_odd: false,
+            bounds: Rect::new(49.5, 59.5, 250.5, 130.5),
+        };
+        let tables = text_page.find_tables(&[grid], &TableOptions::default());
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!((table.row_count(), table.column_count()), (2, 2));
+        assert_eq!(table.bbox, Rect::new(50.0, 60.0, 250.0, 130.0));
+        assert_eq!(table.cell(0, 0).unwrap().text, "Dummy");
+        assert_eq!(table.cell(0, 1).unwrap().text, "PDF file");
+    }
+
+    #[test]
+    fn test_find_horizontally_ruled_table() {
+        let content = format!(
+            "{} 0 G 0.5 w 10 263 m 280 263 l S 10 243 m 280 243 l S \
+             10 223 m 280 223 l S 10 203 m 280 203 l S",
+            ROWS
+        );
+        let doc = pdf_with_content(&content);
+        let page0 = doc.load_page(0).unwrap();
+
+        let tables = page0.find_tables(&TableOptions::default()).unwrap();
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert!(table.ruled);
+        assert_eq!((table.row_count(), table.column_count()), (3, 3));
+        assert_eq!(table.bbox, Rect::new(10.0, 37.0, 280.0, 97.0));
+        let texts: Vec<Vec<&str>> = table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.text.as_str()).collect())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                vec!["Date", "Item", "Total"],
+                vec!["Mon", "Tea", "3"],
+                vec!["Tue", "Cake", "5"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_text_table() {
+        let doc = pdf_with_content(ROWS);
+        let page0 = doc.load_page(0).unwrap();
+
+        // Alignment alone is only used when asked for
+        assert!(page0
+            .find_tables(&TableOptions::default())
+            .unwrap()
+            .is_empty());
+
+        let mut options = TableOptions::default();
+        options.set_text_tables(true);
+        let tables = page0.find_tables(&options).unwrap();
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert!(!table.ruled);
+        assert_eq!((table.row_count(), table.column_count()), (3, 3));
+        let texts: Vec<Vec<&str>> = table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.text.as_str()).collect())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                vec!["Date", "Item", "Total"],
+                vec!["Mon", "Tea", "3"],
+                vec!["Tue", "Cake", "5"]
+            ]
+        );
+    }
+}
@@ -12,6 +12,10 @@ pub struct Text {
 }
 
 impl Text {
+    pub(crate) unsafe fn from_raw(ptr: *mut fz_text) -> Self {
+        Self { inner: ptr }
+    }
+
     pub fn new() -> Result<Self, Error> {
         let inner = unsafe { ffi_try!(mupdf_new_text(context())) };
         Ok(Self { inner })
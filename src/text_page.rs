@@ -1,7 +1,8 @@
 use std::convert::TryInto;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::io::Read;
 use std::marker::PhantomData;
+use std::os::raw::c_char;
 use std::ptr;
 use std::slice;
 
@@ -9,7 +10,7 @@ use bitflags::bitflags;
 use mupdf_sys::*;
 use num_enum::TryFromPrimitive;
 
-use crate::{context, Buffer, Error, Image, Matrix, Point, Quad, Rect, WriteMode};
+use crate::{context, Buffer, Error, Font, Image, Matrix, Point, Quad, Rect, WriteMode};
 
 bitflags! {
     /// Options for creating a pixmap and draw device.
@@ -23,6 +24,21 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Properties of a single [`TextChar`]
+    pub struct TextCharFlags: u32 {
+        /// Not present in the content, e.g. a space inserted between words
+        const SYNTHETIC = 1;
+        /// A ligature such as "fi", only kept with [`TextPageOptions::PRESERVE_LIGATURES`]
+        const LIGATURE = 2;
+        /// A hyphen ending its line
+        const HYPHEN = 4;
+    }
+}
+
+// FZ_STEXT_SYNTHETIC, not available in older MuPDF headers
+const STEXT_SYNTHETIC: i32 = 4;
+
 /// A text page is a list of blocks, together with an overall bounding box
 #[derive(Debug)]
 pub struct TextPage {
@@ -44,6 +60,10 @@ impl TextPage {
         Ok(text)
     }
 
+    pub fn bounds(&self) -> Rect {
+        unsafe { (*self.inner).mediabox.into() }
+    }
+
     pub fn blocks(&self) -> TextBlockIter {
         TextBlockIter {
             next: unsafe { (*self.inner).first_block },
@@ -51,17 +71,33 @@ impl TextPage {
         }
     }
 
-    pub fn search(&self, needle: &str, hit_max: u32) -> Result<Vec<Quad>, Error> {
-        struct Quads(*mut fz_quad);
+    /// Words of all lines separated by whitespace, in reading order
+    pub fn words(&self) -> TextWordIter {
+        self.words_with_delimiters(&[])
+    }
 
-        impl Drop for Quads {
-            fn drop(&mut self) {
-                if !self.0.is_null() {
-                    unsafe { fz_free(context(), self.0 as _) };
-                }
-            }
+    /// Words of all lines separated by whitespace or any of `delimiters`
+    pub fn words_with_delimiters(&self, delimiters: &[char]) -> TextWordIter {
+        TextWordIter {
+            blocks: self.blocks(),
+            lines: TextLineIter {
+                next: ptr::null_mut(),
+                _marker: PhantomData,
+            },
+            chars: TextCharIter {
+                next: ptr::null_mut(),
+                end: ptr::null_mut(),
+                _marker: PhantomData,
+            },
+            block: 0,
+            line: 0,
+            next_block: 0,
+            next_line: 0,
+            delimiters: delimiters.to_vec(),
         }
+    }
 
+    pub fn search(&self, needle: &str, hit_max: u32) -> Result<Vec<Quad>, Error> {
         let c_needle = CString::new(needle)?;
         let hit_max = if hit_max < 1 { 16 } else { hit_max };
         let mut hit_count = 0;
@@ -80,6 +116,93 @@ impl TextPage {
             Ok(items.iter().map(|quad| (*quad).into()).collect())
         }
     }
+
+    /// Move the selection end points `a` and `b` to the boundaries of `mode`
+    ///
+    /// Returns the quad of the snapped selection.
+    pub fn snap_selection(
+        &self,
+        a: &mut Point,
+        b: &mut Point,
+        mode: SelectionMode,
+    ) -> Result<Quad, Error> {
+        let mut fa: fz_point = (*a).into();
+        let mut fb: fz_point = (*b).into();
+        let quad = unsafe {
+            ffi_try!(mupdf_snap_selection(
+                context(),
+                self.inner,
+                &mut fa,
+                &mut fb,
+                mode as _
+            ))
+        };
+        *a = fa.into();
+        *b = fb.into();
+        Ok(quad.into())
+    }
+
+    /// Quads covering the text selected from `a` to `b` in reading order
+    pub fn highlight(&self, a: Point, b: Point, mode: SelectionMode) -> Result<Vec<Quad>, Error> {
+        let mut count = 0;
+        unsafe {
+            let quads = Quads(ffi_try!(mupdf_highlight_selection(
+                context(),
+                self.inner,
+                a.into(),
+                b.into(),
+                mode as _,
+                &mut count
+            )));
+            if count == 0 {
+                return Ok(Vec::new());
+            }
+            let items = slice::from_raw_parts(quads.0, count as usize);
+            Ok(items.iter().map(|quad| (*quad).into()).collect())
+        }
+    }
+
+    /// Text selected from `a` to `b` in reading order
+    pub fn copy_selection(&self, a: Point, b: Point, mode: SelectionMode) -> Result<String, Error> {
+        unsafe {
+            let ptr = ffi_try!(mupdf_copy_selection(
+                context(),
+                self.inner,
+                a.into(),
+                b.into(),
+                mode as _
+            ));
+            Ok(take_string(ptr))
+        }
+    }
+
+    /// Text of all characters inside `area`
+    pub fn copy_rectangle(&self, area: Rect) -> Result<String, Error> {
+        unsafe {
+            let ptr = ffi_try!(mupdf_copy_rectangle(context(), self.inner, area.into()));
+            Ok(take_string(ptr))
+        }
+    }
+}
+
+struct Quads(*mut fz_quad);
+
+impl Drop for Quads {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { fz_free(context(), self.0 as _) };
+        }
+    }
+}
+
+/// Copy a string allocated by MuPDF and free it
+unsafe fn take_string(ptr: *mut c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    let s = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+    fz_free(context(), ptr as _);
+    s
 }
 
 impl Drop for TextPage {
@@ -99,12 +222,21 @@ pub enum TextBlockType {
     Image = FZ_STEXT_BLOCK_IMAGE as u32,
 }
 
+/// Granularity a selection snaps to
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u32)]
+pub enum SelectionMode {
+    Chars = FZ_SELECT_CHARS as u32,
+    Words = FZ_SELECT_WORDS as u32,
+    Lines = FZ_SELECT_LINES as u32,
+}
+
 /// A text block is a list of lines of text (typically a paragraph), or an image.
 pub struct TextBlock<'a> {
     inner: &'a fz_stext_block,
 }
 
-impl TextBlock<'_> {
+impl<'a> TextBlock<'a> {
     pub fn r#type(&self) -> TextBlockType {
         (self.inner.type_ as u32).try_into().unwrap()
     }
@@ -113,7 +245,7 @@ impl TextBlock<'_> {
         self.inner.bbox.into()
     }
 
-    pub fn lines(&self) -> TextLineIter {
+    pub fn lines(&self) -> TextLineIter<'a> {
         unsafe {
             if self.inner.type_ == FZ_STEXT_BLOCK_TEXT as i32 {
                 return TextLineIter {
@@ -174,7 +306,7 @@ pub struct TextLine<'a> {
     inner: &'a fz_stext_line,
 }
 
-impl TextLine<'_> {
+impl<'a> TextLine<'a> {
     pub fn bounds(&self) -> Rect {
         self.inner.bbox.into()
     }
@@ -183,12 +315,51 @@ impl TextLine<'_> {
         (self.inner.wmode as u32).try_into().unwrap()
     }
 
-    pub fn chars(&self) -> TextCharIter {
+    /// Normalized direction of the baseline
+    pub fn dir(&self) -> Point {
+        self.inner.dir.into()
+    }
+
+    pub fn chars(&self) -> TextCharIter<'a> {
         TextCharIter {
+            next: self.inner.first_char,
+            end: ptr::null_mut(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Runs of consecutive chars sharing font, size, color and bidi level
+    pub fn runs(&self) -> TextRunIter<'a> {
+        TextRunIter {
             next: self.inner.first_char,
             _marker: PhantomData,
         }
     }
+
+    /// Words of this line separated by whitespace, block and line indices are 0
+    pub fn words(&self) -> TextWordIter<'a> {
+        self.words_with_delimiters(&[])
+    }
+
+    /// Words of this line separated by whitespace or any of `delimiters`
+    pub fn words_with_delimiters(&self, delimiters: &[char]) -> TextWordIter<'a> {
+        TextWordIter {
+            blocks: TextBlockIter {
+                next: ptr::null_mut(),
+                _marker: PhantomData,
+            },
+            lines: TextLineIter {
+                next: ptr::null_mut(),
+                _marker: PhantomData,
+            },
+            chars: self.chars(),
+            block: 0,
+            line: 0,
+            next_block: 1,
+            next_line: 1,
+            delimiters: delimiters.to_vec(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -233,11 +404,58 @@ impl TextChar<'_> {
     pub fn quad(&self) -> Quad {
         self.inner.quad.into()
     }
+
+    pub fn font(&self) -> Font {
+        unsafe {
+            fz_keep_font(context(), self.inner.font);
+            Font::from_raw(self.inner.font)
+        }
+    }
+
+    /// Fill color in sRGB as `0xAARRGGBB`
+    pub fn color(&self) -> u32 {
+        self.style().2
+    }
+
+    /// Bidirectional embedding level, even for left-to-right and odd for right-to-left
+    pub fn bidi_level(&self) -> u32 {
+        self.style().0
+    }
+
+    pub fn flags(&self) -> TextCharFlags {
+        let mut flags = TextCharFlags::empty();
+        if self.style().1 & STEXT_SYNTHETIC != 0 {
+            flags |= TextCharFlags::SYNTHETIC;
+        }
+        match self.char() {
+            Some('\u{FB00}'..='\u{FB06}') => flags |= TextCharFlags::LIGATURE,
+            Some('-' | '\u{00AD}' | '\u{2010}') if self.inner.next.is_null() => {
+                flags |= TextCharFlags::HYPHEN
+            }
+            _ => {}
+        }
+        flags
+    }
+
+    /// `(bidi, flags, argb)` as stored by MuPDF
+    fn style(&self) -> (u32, i32, u32) {
+        let (mut bidi, mut flags, mut argb) = (0, 0, 0);
+        unsafe { mupdf_stext_char_style(self.inner, &mut bidi, &mut flags, &mut argb) };
+        (bidi as u32, flags, argb)
+    }
+
+    fn same_style(&self, other: &TextChar) -> bool {
+        self.inner.font == other.inner.font
+            && self.inner.size == other.inner.size
+            && self.color() == other.color()
+            && self.bidi_level() == other.bidi_level()
+    }
 }
 
 #[derive(Debug)]
 pub struct TextCharIter<'a> {
     next: *mut fz_stext_char,
+    end: *mut fz_stext_char,
     _marker: PhantomData<TextChar<'a>>,
 }
 
@@ -245,7 +463,7 @@ impl<'a> Iterator for TextCharIter<'a> {
     type Item = TextChar<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.next.is_null() {
+        if self.next.is_null() || self.next == self.end {
             return None;
         }
         let node = unsafe { &*self.next };
@@ -254,6 +472,169 @@ impl<'a> Iterator for TextCharIter<'a> {
     }
 }
 
+/// Consecutive chars of a [`TextLine`] in the same style
+#[derive(Debug)]
+pub struct TextRun<'a> {
+    first: &'a fz_stext_char,
+    end: *mut fz_stext_char,
+}
+
+impl<'a> TextRun<'a> {
+    fn first(&self) -> TextChar<'a> {
+        TextChar { inner: self.first }
+    }
+
+    pub fn chars(&self) -> TextCharIter<'a> {
+        TextCharIter {
+            next: self.first as *const _ as *mut _,
+            end: self.end,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.chars().filter_map(|ch| ch.char()).collect()
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let mut bounds = Rect::default();
+        for ch in self.chars() {
+            bounds.union(ch.quad().into());
+        }
+        bounds
+    }
+
+    pub fn font(&self) -> Font {
+        self.first().font()
+    }
+
+    pub fn size(&self) -> f32 {
+        self.first().size()
+    }
+
+    /// Fill color in sRGB as `0xAARRGGBB`
+    pub fn color(&self) -> u32 {
+        self.first().color()
+    }
+
+    pub fn bidi_level(&self) -> u32 {
+        self.first().bidi_level()
+    }
+}
+
+#[derive(Debug)]
+pub struct TextRunIter<'a> {
+    next: *mut fz_stext_char,
+    _marker: PhantomData<TextRun<'a>>,
+}
+
+impl<'a> Iterator for TextRunIter<'a> {
+    type Item = TextRun<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+        let first: &'a fz_stext_char = unsafe { &*self.next };
+        let style = TextChar { inner: first };
+        let mut end = first.next;
+        while !end.is_null()
+            && style.same_style(&TextChar {
+                inner: unsafe { &*end },
+            })
+        {
+            end = unsafe { (*end).next };
+        }
+        self.next = end;
+        Some(TextRun { first, end })
+    }
+}
+
+/// A word of a [`TextPage`] with the position of its line
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextWord {
+    pub text: String,
+    pub bounds: Rect,
+    /// From the start of the first char to the end of the last char, follows rotated text
+    pub quad: Quad,
+    /// Index of the block in the page
+    pub block: usize,
+    /// Index of the line in its block
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub struct TextWordIter<'a> {
+    blocks: TextBlockIter<'a>,
+    lines: TextLineIter<'a>,
+    chars: TextCharIter<'a>,
+    block: usize,
+    line: usize,
+    next_block: usize,
+    next_line: usize,
+    delimiters: Vec<char>,
+}
+
+fn is_delimiter(delimiters: &[char], c: Option<char>) -> bool {
+    match c {
+        Some(c) => c.is_whitespace() || delimiters.contains(&c),
+        None => false,
+    }
+}
+
+impl Iterator for TextWordIter<'_> {
+    type Item = TextWord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut word: Option<TextWord> = None;
+            for ch in self.chars.by_ref() {
+                let c = ch.char();
+                if is_delimiter(&self.delimiters, c) {
+                    if word.is_some() {
+                        return word;
+                    }
+                    continue;
+                }
+                let quad = ch.quad();
+                let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+                match &mut word {
+                    Some(word) => {
+                        word.text.push(c);
+                        word.bounds.union(quad.clone().into());
+                        word.quad.ur = quad.ur;
+                        word.quad.lr = quad.lr;
+                    }
+                    None => {
+                        word = Some(TextWord {
+                            text: c.to_string(),
+                            bounds: quad.clone().into(),
+                            quad,
+                            block: self.block,
+                            line: self.line,
+                        })
+                    }
+                }
+            }
+            if word.is_some() {
+                return word;
+            }
+
+            if let Some(line) = self.lines.next() {
+                self.line = self.next_line;
+                self.next_line += 1;
+                self.chars = line.chars();
+            } else {
+                let block = self.blocks.next()?;
+                self.block = self.next_block;
+                self.next_block += 1;
+                self.next_line = 0;
+                self.lines = block.lines();
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{Document, TextPageOptions};
@@ -292,4 +673,91 @@ mod test {
         let hits = text_page.search("Not Found", 1).unwrap();
         assert_eq!(hits.len(), 0);
     }
+
+    #[test]
+    fn test_text_page_styles() {
+        use super::TextCharFlags;
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let text_page = page0.to_text_page(TextPageOptions::empty()).unwrap();
+        let line = text_page
+            .blocks()
+            .flat_map(|block| block.lines().collect::<Vec<_>>())
+            .next()
+            .unwrap();
+
+        let runs: Vec<_> = line.runs().collect();
+        assert!(!runs.is_empty());
+        let text: String = runs.iter().map(|run| run.text()).collect();
+        let line_text: String = line.chars().filter_map(|ch| ch.char()).collect();
+        assert_eq!(text, line_text);
+        assert!(text.contains("Dummy"));
+
+        let run = &runs[0];
+        assert!(!run.font().name().is_empty());
+        assert!(run.size() > 0.0);
+        // Black, opaque
+        assert_eq!(run.color(), 0xff000000);
+        assert_eq!(run.bidi_level() % 2, 0);
+        let ch = run.chars().next().unwrap();
+        assert!(!ch.flags().contains(TextCharFlags::LIGATURE));
+    }
+
+    #[test]
+    fn test_text_page_selection() {
+        use super::SelectionMode;
+        use crate::{Point, Rect};
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let text_page = page0.to_text_page(TextPageOptions::empty()).unwrap();
+        let hit = Rect::from(text_page.search("Dummy", 1).unwrap().remove(0));
+
+        let a = Point::new(hit.x0 + 1.0, (hit.y0 + hit.y1) / 2.0);
+        let b = Point::new(hit.x0 + 2.0, (hit.y0 + hit.y1) / 2.0);
+        let word = text_page
+            .copy_selection(a, b, SelectionMode::Words)
+            .unwrap();
+        assert_eq!(word, "Dummy");
+        let quads = text_page.highlight(a, b, SelectionMode::Words).unwrap();
+        assert_eq!(quads.len(), 1);
+
+        let (mut sa, mut sb) = (a, b);
+        text_page
+            .snap_selection(&mut sa, &mut sb, SelectionMode::Words)
+            .unwrap();
+        assert!(sa.x <= a.x && sb.x >= b.x);
+
+        let text = text_page.copy_rectangle(hit).unwrap();
+        assert!(text.contains("Dummy"));
+    }
+
+    #[test]
+    fn test_text_page_words() {
+        use crate::Rect;
+
+        let doc = Document::open("tests/files/dummy.pdf").unwrap();
+        let page0 = doc.load_page(0).unwrap();
+        let text_page = page0.to_text_page(TextPageOptions::empty()).unwrap();
+
+        let words: Vec<_> = text_page.words().collect();
+        let text: Vec<_> = words.iter().map(|word| word.text.as_str()).collect();
+        assert_eq!(text, ["Dummy", "PDF", "file"]);
+        assert!(words.iter().all(|word| (word.block, word.line) == (0, 0)));
+
+        let hit = Rect::from(text_page.search("Dummy", 1).unwrap().remove(0));
+        let bounds = words[0].bounds;
+        assert!((bounds.x0 - hit.x0).abs() < 0.01 && (bounds.x1 - hit.x1).abs() < 0.01);
+        assert_eq!(Rect::from(words[0].quad.clone()), bounds);
+
+        let words: Vec<_> = text_page
+            .words_with_delimiters(&['D'])
+            .map(|word| word.text)
+            .collect();
+        assert_eq!(words, ["ummy", "P", "F", "file"]);
+
+        let line = text_page.blocks().next().unwrap().lines().next().unwrap();
+        assert_eq!(line.words().count(), 3);
+    }
 }